@@ -0,0 +1,207 @@
+// ⏱️ A small criterion-style harness on pure std
+//
+// Each benchmark is warmed up, then timed over a fixed number of samples
+// whose iteration count is calibrated so every sample takes about the same
+// time. The median throughput is what gets reported, saved and compared:
+// it shrugs off the odd sample stretched by a context switch.
+//
+//   cargo bench --bench kernel -- [FILTER] [--quick]
+//       [--save-baseline FILE] [--baseline FILE [--threshold PCT]]
+//
+// `--baseline` compares against a file written by `--save-baseline` and
+// exits with status 1 if any benchmark got slower by more than the threshold
+// (10% by default).
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+pub struct Options {
+    filter: Option<String>,
+    quick: bool,
+    save_baseline: Option<String>,
+    baseline: Option<String>,
+    threshold: f64,
+}
+
+impl Options {
+    fn from_args() -> Result<Self, String> {
+        let mut options = Options {
+            filter: None,
+            quick: false,
+            save_baseline: None,
+            baseline: None,
+            threshold: 10.0,
+        };
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} requires a value"));
+            match arg.as_str() {
+                // Passed by `cargo bench`
+                "--bench" => {}
+                "--quick" => options.quick = true,
+                "--save-baseline" => options.save_baseline = Some(value()?),
+                "--baseline" => options.baseline = Some(value()?),
+                "--threshold" => {
+                    let threshold = value()?;
+                    options.threshold = threshold
+                        .parse()
+                        .map_err(|_| format!("Invalid --threshold value: {threshold}"))?;
+                }
+                flag if flag.starts_with("--") => return Err(format!("Unknown option: {flag}")),
+                filter => options.filter = Some(filter.to_string()),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub struct Harness {
+    options: Options,
+    baseline: BTreeMap<String, f64>,
+    // Median GB/s of every benchmark run so far, by name
+    results: BTreeMap<String, f64>,
+    regressions: Vec<String>,
+}
+
+impl Harness {
+    pub fn from_args() -> Self {
+        let options = Options::from_args().unwrap_or_else(|e| {
+            eprintln!("Error: {e}");
+            std::process::exit(2);
+        });
+        let baseline = match &options.baseline {
+            Some(path) => read_baseline(path).unwrap_or_else(|e| {
+                eprintln!("Error: {path}: {e}");
+                std::process::exit(2);
+            }),
+            None => BTreeMap::new(),
+        };
+        Harness {
+            options,
+            baseline,
+            results: BTreeMap::new(),
+            regressions: Vec::new(),
+        }
+    }
+
+    // Times `routine`, which processes `bytes` bytes per call
+    pub fn bench<T>(&mut self, name: &str, bytes: usize, mut routine: impl FnMut() -> T) {
+        if let Some(filter) = &self.options.filter {
+            if !name.contains(filter.as_str()) {
+                return;
+            }
+        }
+        let (warm_up, measurement, samples) = if self.options.quick {
+            (Duration::from_millis(50), Duration::from_millis(200), 10)
+        } else {
+            (Duration::from_millis(300), Duration::from_millis(1500), 30)
+        };
+
+        // Warm up, and learn roughly how long one call takes
+        let started = Instant::now();
+        let mut calls = 0u64;
+        while started.elapsed() < warm_up {
+            black_box(routine());
+            calls += 1;
+        }
+        let per_call = started.elapsed().as_secs_f64() / calls as f64;
+        let sample_time = measurement.as_secs_f64() / samples as f64;
+        let iterations = ((sample_time / per_call) as u64).max(1);
+
+        let mut seconds_per_call: Vec<f64> = (0..samples)
+            .map(|_| {
+                let started = Instant::now();
+                for _ in 0..iterations {
+                    black_box(routine());
+                }
+                started.elapsed().as_secs_f64() / iterations as f64
+            })
+            .collect();
+        seconds_per_call.sort_by(f64::total_cmp);
+
+        let median = seconds_per_call[samples / 2];
+        let fastest = seconds_per_call[0];
+        let slowest = seconds_per_call[samples - 1];
+        let gbps = bytes as f64 / median / 1e9;
+        println!(
+            "{name:<28} time: [{} {} {}]  thrpt: {gbps:.3} GB/s",
+            format_time(fastest),
+            format_time(median),
+            format_time(slowest)
+        );
+
+        if let Some(&before) = self.baseline.get(name) {
+            let change = (gbps - before) / before * 100.0;
+            let verdict = if change < -self.options.threshold {
+                self.regressions.push(name.to_string());
+                "REGRESSED"
+            } else if change > self.options.threshold {
+                "improved"
+            } else {
+                "within threshold"
+            };
+            println!(
+                "{:<28} change: {change:+.1}% vs {before:.3} GB/s, {verdict}",
+                ""
+            );
+        }
+        self.results.insert(name.to_string(), gbps);
+    }
+
+    // Saves the baseline if asked to, and fails the run on any regression
+    pub fn finish(self) {
+        if let Some(path) = &self.options.save_baseline {
+            if let Err(e) = std::fs::write(path, format_baseline(&self.results)) {
+                eprintln!("Error: {path}: {e}");
+                std::process::exit(2);
+            }
+            println!("Saved {} results to {path}", self.results.len());
+        }
+        if !self.regressions.is_empty() {
+            eprintln!(
+                "{} benchmark(s) regressed by more than {}%: {}",
+                self.regressions.len(),
+                self.options.threshold,
+                self.regressions.join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    match seconds {
+        s if s < 1e-6 => format!("{:.2} ns", s * 1e9),
+        s if s < 1e-3 => format!("{:.2} µs", s * 1e6),
+        s if s < 1.0 => format!("{:.2} ms", s * 1e3),
+        s => format!("{s:.2} s"),
+    }
+}
+
+// One `name GB/s` pair per line; `#` starts a comment
+fn format_baseline(results: &BTreeMap<String, f64>) -> String {
+    let mut baseline = String::from("# benchmark median-GB/s\n");
+    for (name, gbps) in results {
+        baseline.push_str(&format!("{name} {gbps:.4}\n"));
+    }
+    baseline
+}
+
+fn read_baseline(path: &str) -> Result<BTreeMap<String, f64>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, gbps) = line
+                .split_once(' ')
+                .ok_or_else(|| format!("malformed line: {line}"))?;
+            let gbps = gbps
+                .trim()
+                .parse()
+                .map_err(|_| format!("malformed line: {line}"))?;
+            Ok((name.to_string(), gbps))
+        })
+        .collect()
+}
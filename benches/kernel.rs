@@ -0,0 +1,69 @@
+// 🚀 Throughput of `count_bytes_blazing_speed` across input shapes and sizes
+//
+//   cargo bench --bench kernel
+//   cargo bench --bench kernel -- --save-baseline main.txt
+//   cargo bench --bench kernel -- --baseline main.txt --threshold 5
+
+mod harness;
+
+use harness::Harness;
+use std::hint::black_box;
+use ultra_blazing_wc::count_bytes_blazing_speed;
+
+const SHAPES: [&str; 5] = ["ascii", "utf8", "whitespace", "one-word", "binary"];
+const SIZES: [(usize, &str); 4] = [
+    (1 << 10, "1KiB"),
+    (64 << 10, "64KiB"),
+    (1 << 20, "1MiB"),
+    (16 << 20, "16MiB"),
+];
+
+// Deterministic input of the given shape, from a fixed xorshift seed
+fn input(shape: &str, size: usize) -> Vec<u8> {
+    const UTF8: [&str; 6] = ["é", "ж", "λ", "中", "한", "😀"];
+
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut data = Vec::with_capacity(size + 8);
+    while data.len() < size {
+        let random = next();
+        match shape {
+            // Words of 1-8 letters, a newline every few words
+            "ascii" => {
+                let letters = 1 + random as usize % 8;
+                data.extend((0..letters).map(|i| b'a' + (random >> (8 + i * 4)) as u8 % 26));
+                data.push(if random >> 60 == 0 { b'\n' } else { b' ' });
+            }
+            "utf8" => {
+                for i in 0..1 + random as usize % 4 {
+                    data.extend_from_slice(UTF8[(random >> (8 + i * 4)) as usize % 6].as_bytes());
+                }
+                data.push(if random >> 60 == 0 { b'\n' } else { b' ' });
+            }
+            "whitespace" => data.push(b" \t\n\r"[random as usize % 4]),
+            // No separator at all: worst case for the word state machine
+            "one-word" => data.push(b'a' + random as u8 % 26),
+            _ => data.extend_from_slice(&random.to_le_bytes()),
+        }
+    }
+    data.truncate(size);
+    data
+}
+
+fn main() {
+    let mut harness = Harness::from_args();
+    for shape in SHAPES {
+        for (size, label) in SIZES {
+            let data = input(shape, size);
+            harness.bench(&format!("kernel/{shape}/{label}"), size, || {
+                count_bytes_blazing_speed(black_box(&data))
+            });
+        }
+    }
+    harness.finish();
+}
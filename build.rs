@@ -0,0 +1,147 @@
+// 🌐 Turns the checked-in Unicode data files into lookup tables at build time
+//
+// One sorted range table maps every code point to its grapheme cluster break
+// category and display width, so segmentation and width share one lookup.
+// Only the files under unicode/ are read: the build never needs the network.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const MAX_CODE_POINT: usize = 0x10FFFF;
+
+// (UCD value, `Gcb` variant), in the order of the enum in src/graphemes.rs
+const CATEGORIES: &[(&str, &str)] = &[
+    ("Other", "Other"),
+    ("CR", "Cr"),
+    ("LF", "Lf"),
+    ("Control", "Control"),
+    ("Extend", "Extend"),
+    ("ZWJ", "Zwj"),
+    ("Regional_Indicator", "RegionalIndicator"),
+    ("Prepend", "Prepend"),
+    ("SpacingMark", "SpacingMark"),
+    ("L", "L"),
+    ("V", "V"),
+    ("T", "T"),
+    ("LV", "Lv"),
+    ("LVT", "Lvt"),
+    ("Extended_Pictographic", "ExtendedPictographic"),
+];
+
+// Categories that never take up a terminal column on their own
+const ZERO_WIDTH: &[&str] = &["CR", "LF", "Control", "Extend", "ZWJ", "V", "T"];
+
+// Yields (first, last, value) for every data line of a UCD file in unicode/
+fn ucd_entries(name: &str) -> Vec<(usize, usize, String)> {
+    // The fuzz crate in fuzz/ runs this script too, one directory down
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let root = Path::new(&manifest_dir);
+    let file = [root, root.parent().unwrap_or(root)]
+        .iter()
+        .map(|dir| dir.join("unicode").join(name))
+        .find(|file| file.exists())
+        .unwrap_or_else(|| panic!("unicode/{name} not found"));
+    let path = file.display();
+
+    println!("cargo:rerun-if-changed={path}");
+    let text = fs::read_to_string(&file).unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+
+    let mut entries = Vec::new();
+    for line in text.lines() {
+        let data = line.split('#').next().unwrap().trim();
+        let Some((range, value)) = data.split_once(';') else {
+            continue;
+        };
+        let parse = |hex: &str| {
+            usize::from_str_radix(hex.trim(), 16)
+                .unwrap_or_else(|e| panic!("{path}: bad code point {hex:?}: {e}"))
+        };
+        let (first, last) = match range.split_once("..") {
+            Some((first, last)) => (parse(first), parse(last)),
+            None => (parse(range), parse(range)),
+        };
+        entries.push((first, last, value.trim().to_string()));
+    }
+    entries
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let mut category = vec![0u8; MAX_CODE_POINT + 1];
+    let mut wide = vec![false; MAX_CODE_POINT + 1];
+
+    for (first, last, value) in ucd_entries("GraphemeBreakProperty.txt") {
+        let index = CATEGORIES
+            .iter()
+            .position(|&(name, _)| name == value)
+            .unwrap_or_else(|| panic!("unknown Grapheme_Cluster_Break value {value:?}"));
+        category[first..=last].fill(index as u8);
+    }
+
+    // Extended_Pictographic code points are all Other for Grapheme_Cluster_Break
+    let pictographic = CATEGORIES.len() as u8 - 1;
+    for (first, last, value) in ucd_entries("emoji-data.txt") {
+        if value == "Extended_Pictographic" {
+            for slot in &mut category[first..=last] {
+                if *slot == 0 {
+                    *slot = pictographic;
+                }
+            }
+        }
+    }
+
+    for (first, last, value) in ucd_entries("EastAsianWidth.txt") {
+        if value == "W" || value == "F" {
+            wide[first..=last].fill(true);
+        }
+    }
+
+    let width = |cp: usize| -> u8 {
+        let (name, _) = CATEGORIES[category[cp] as usize];
+        if ZERO_WIDTH.contains(&name) {
+            0
+        } else if wide[cp] || name == "Regional_Indicator" {
+            2
+        } else {
+            1
+        }
+    };
+
+    let mut table = String::new();
+    let mut run: Option<(usize, usize, u8, u8)> = None;
+    let flush = |run: (usize, usize, u8, u8), table: &mut String| {
+        let (first, last, category, width) = run;
+        // Other with width 1 is the default and needs no entry
+        if category != 0 || width != 1 {
+            let (_, name) = CATEGORIES[category as usize];
+            table.push_str(&format!(
+                "    (0x{first:X}, 0x{last:X}, Gcb::{name}, {width}),\n"
+            ));
+        }
+    };
+    for cp in 0..=MAX_CODE_POINT {
+        let value = (category[cp], width(cp));
+        match &mut run {
+            Some((_, last, category, width)) if (*category, *width) == value => *last = cp,
+            _ => {
+                if let Some(done) = run.take() {
+                    flush(done, &mut table);
+                }
+                run = Some((cp, cp, value.0, value.1));
+            }
+        }
+    }
+    flush(run.unwrap(), &mut table);
+
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("unicode_tables.rs");
+    fs::write(
+        out,
+        format!(
+            "// Generated by build.rs from unicode/*.txt\n\
+             const UNICODE_TABLE: &[(u32, u32, Gcb, u8)] = &[\n{table}];\n"
+        ),
+    )
+    .unwrap();
+}
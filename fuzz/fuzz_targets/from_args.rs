@@ -0,0 +1,18 @@
+// ⌨️ `Config::parse` on arbitrary argument lists, NUL-separated in the input
+//
+// Errors are fine; panics are not. The binary's sources are compiled in
+// whole, with `main` left unused.
+#![no_main]
+#![allow(dead_code)]
+
+include!("../../src/main.rs");
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    let args: Vec<String> = std::iter::once("wc".to_string())
+        .chain(
+            data.split(|&byte| byte == 0)
+                .map(|arg| String::from_utf8_lossy(arg).into_owned()),
+        )
+        .collect();
+    let _ = Config::parse(args);
+});
@@ -0,0 +1,10 @@
+// 🚀 The unrolled, `get_unchecked` kernel against the reference counter
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ultra_blazing_wc::count_bytes_blazing_speed;
+use ultra_blazing_wc_fuzz::reference;
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(count_bytes_blazing_speed(data), reference(data));
+});
@@ -0,0 +1,32 @@
+// 🧩 `Counter` and `CountingReader` fed the input in random chunks
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use std::io::{BufRead, Write};
+use ultra_blazing_wc::{Counter, CountingReader};
+use ultra_blazing_wc_fuzz::{chunks, reference, split_input};
+
+fuzz_target!(|data: &[u8]| {
+    let (cuts, payload) = split_input(data);
+    let expected = reference(payload);
+
+    let mut counter = Counter::new();
+    for chunk in chunks(&cuts, payload) {
+        counter.feed(chunk);
+    }
+    assert_eq!(counter.finish(), expected);
+
+    let mut writer = Counter::new();
+    for chunk in chunks(&cuts, payload) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(writer.snapshot(), expected);
+
+    // `BufRead` consuming one chunk's worth at a time
+    let mut reader = CountingReader::new(payload);
+    for chunk in chunks(&cuts, payload) {
+        let available = reader.fill_buf().unwrap().len();
+        reader.consume(chunk.len().min(available));
+    }
+    assert_eq!(reader.counts(), expected);
+});
@@ -0,0 +1,61 @@
+// ✅ UTF-8 validation and transcoding, fed in random chunks
+//
+// The validator must agree with std on where the first error is and with
+// `from_utf8_lossy` on how many there are; every decoder must emit valid
+// UTF-8 however its input is cut up.
+#![no_main]
+
+#[path = "../../src/encoding.rs"]
+#[allow(dead_code)]
+mod encoding;
+#[path = "../../src/utf8.rs"]
+mod utf8;
+
+use encoding::{Decoder, Encoding};
+use libfuzzer_sys::fuzz_target;
+use ultra_blazing_wc_fuzz::{chunks, split_input};
+use utf8::Utf8Validator;
+
+fuzz_target!(|data: &[u8]| {
+    let (cuts, payload) = split_input(data);
+
+    let mut validator = Utf8Validator::new();
+    for chunk in chunks(&cuts, payload) {
+        validator.feed(chunk);
+    }
+    let report = validator.finish();
+
+    let invalid = payload
+        .utf8_chunks()
+        .filter(|chunk| !chunk.invalid().is_empty())
+        .count() as u64;
+    assert_eq!(report.invalid, invalid);
+    let first_invalid = std::str::from_utf8(payload).err().map(|e| {
+        let offset = e.valid_up_to();
+        let line = 1 + payload[..offset].iter().filter(|&&b| b == b'\n').count();
+        (offset as u64, line as u64)
+    });
+    assert_eq!(report.first_invalid, first_invalid);
+
+    for encoding in [
+        Encoding::Utf16Le,
+        Encoding::Utf16Be,
+        Encoding::Utf32Le,
+        Encoding::Utf32Be,
+        Encoding::Latin1,
+    ] {
+        let mut decoder = Decoder::new(encoding);
+        let mut decoded = Vec::new();
+        for chunk in chunks(&cuts, payload) {
+            decoder.decode(chunk, &mut decoded);
+        }
+        decoder.finish(&mut decoded);
+        assert!(std::str::from_utf8(&decoded).is_ok(), "{encoding:?}");
+
+        let mut whole = Vec::new();
+        let mut decoder = Decoder::new(encoding);
+        decoder.decode(payload, &mut whole);
+        decoder.finish(&mut whole);
+        assert_eq!(decoded, whole, "{encoding:?} depends on chunking");
+    }
+});
@@ -0,0 +1,46 @@
+// 🐛 Shared by the fuzz targets: the reference counter and input splitting
+
+use ultra_blazing_wc::Counts;
+
+// Lines are '\n' bytes; words are maximal runs of bytes above b' '. Slow and
+// obviously correct, unlike the unrolled kernel it checks.
+pub fn reference(data: &[u8]) -> Counts {
+    Counts {
+        lines: data.iter().filter(|&&byte| byte == b'\n').count() as u64,
+        words: data
+            .split(|&byte| byte <= b' ')
+            .filter(|word| !word.is_empty())
+            .count() as u64,
+        chars: data.len() as u64,
+    }
+}
+
+// Splits a fuzz input into chunk boundaries and the payload they cut up. The
+// first byte is how many cut points follow (up to 15); each cut point byte is
+// a fraction of the payload length, so cuts stay meaningful as inputs shrink.
+pub fn split_input(data: &[u8]) -> (Vec<usize>, &[u8]) {
+    let Some((&count, rest)) = data.split_first() else {
+        return (Vec::new(), data);
+    };
+    let count = (count as usize % 16).min(rest.len());
+    let (cuts, payload) = rest.split_at(count);
+    let mut cuts: Vec<usize> = cuts
+        .iter()
+        .map(|&cut| cut as usize * payload.len() / 255)
+        .collect();
+    cuts.sort_unstable();
+    cuts.push(payload.len());
+    (cuts, payload)
+}
+
+// The payload cut at every boundary from `split_input`, empty pieces included
+pub fn chunks<'a>(cuts: &[usize], payload: &'a [u8]) -> Vec<&'a [u8]> {
+    let mut start = 0;
+    cuts.iter()
+        .map(|&end| {
+            let chunk = &payload[start..end];
+            start = end;
+            chunk
+        })
+        .collect()
+}
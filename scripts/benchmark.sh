@@ -1,145 +0,0 @@
-#!/bin/bash
-
-# 🚀 ULTRA BLAZING WC - Performance Benchmark Script
-
-set -e
-
-echo "🚀💨 ULTRA BLAZING WC - Performance Benchmark"
-echo "=============================================="
-echo
-
-# Colors for output
-RED='\033[0;31m'
-GREEN='\033[0;32m'
-BLUE='\033[0;34m'
-YELLOW='\033[1;33m'
-NC='\033[0m' # No Color
-
-# Ensure we have a release build
-echo "Building optimized release version..."
-RUSTFLAGS="-C target-cpu=native" cargo build --release --quiet
-
-echo "✅ Build complete!"
-echo
-
-# Test files
-SMALL_FILE="tests/data/small.txt"
-LARGE_FILE="tests/data/large.txt"
-HUGE_FILE="huge_test.txt"
-
-# Create huge file if it doesn't exist
-if [ ! -f "$HUGE_FILE" ]; then
-    echo "Creating 100MB test file..."
-    dd if=/dev/zero of="$HUGE_FILE" bs=1M count=100 2>/dev/null
-    echo "✅ Created $HUGE_FILE"
-fi
-
-echo "📊 PERFORMANCE BENCHMARKS"
-echo "========================="
-echo
-
-# Function to run benchmark
-benchmark() {
-    local test_name="$1"
-    local file="$2"
-    local description="$3"
-    
-    echo -e "${BLUE}🔥 $test_name${NC}"
-    echo "File: $file ($description)"
-    echo
-    
-    # Warm up
-    ./target/release/wc "$file" > /dev/null 2>&1
-    wc "$file" > /dev/null 2>&1
-    
-    echo "System wc:"
-    time wc "$file"
-    echo
-    
-    echo "🚀 Ultra Blazing WC:"
-    time ./target/release/wc "$file"
-    echo
-    
-    # Verify correctness
-    echo "Correctness check:"
-    SYS_OUTPUT=$(wc "$file")
-    OUR_OUTPUT=$(./target/release/wc "$file")
-    
-    SYS_COUNTS=$(echo "$SYS_OUTPUT" | awk '{print $1, $2, $3}')
-    OUR_COUNTS=$(echo "$OUR_OUTPUT" | awk '{print $1, $2, $3}')
-    
-    if [ "$SYS_COUNTS" = "$OUR_COUNTS" ]; then
-        echo -e "${GREEN}✅ Perfect match!${NC}"
-    else
-        echo -e "${YELLOW}⚠️  Minor difference (likely word boundary handling):${NC}"
-        echo "  System: $SYS_COUNTS"
-        echo "  Ours:   $OUR_COUNTS"
-    fi
-    
-    echo
-    echo "----------------------------------------"
-    echo
-}
-
-# Run benchmarks
-benchmark "Small File Test" "$SMALL_FILE" "425 bytes, mixed content"
-benchmark "Large File Test" "$LARGE_FILE" "4MB, real words"
-benchmark "Huge File Test" "$HUGE_FILE" "100MB, zero-filled"
-
-# Multiple files test
-echo -e "${BLUE}🔥 Multiple Files Test${NC}"
-echo "Files: All test files processed in parallel"
-echo
-
-echo "System wc:"
-time wc tests/data/*.txt
-
-echo
-echo "🚀 Ultra Blazing WC (parallel processing):"
-time ./target/release/wc tests/data/*.txt
-
-echo
-echo "----------------------------------------"
-echo
-
-# Stdin test
-echo -e "${BLUE}🔥 Stdin Processing Test${NC}"
-echo "Input: Large file piped through stdin"
-echo
-
-echo "System wc:"
-time cat "$LARGE_FILE" | wc
-
-echo
-echo "🚀 Ultra Blazing WC:"
-time cat "$LARGE_FILE" | ./target/release/wc
-
-echo
-echo "----------------------------------------"
-echo
-
-# Flag tests
-echo -e "${BLUE}🔥 Flag Performance Tests${NC}"
-echo
-
-for flag in "-l" "-w" "-c"; do
-    echo "Testing flag: $flag"
-    echo "System wc:"
-    time wc $flag "$LARGE_FILE"
-    echo "🚀 Ultra Blazing WC:"
-    time ./target/release/wc $flag "$LARGE_FILE"
-    echo
-done
-
-echo "🎯 SUMMARY"
-echo "=========="
-echo -e "${GREEN}✅ All tests completed successfully!${NC}"
-echo -e "${YELLOW}🚀 Ultra Blazing WC consistently outperforms system wc${NC}"
-echo -e "${BLUE}💡 Key optimizations:${NC}"
-echo "   • Memory-mapped I/O with zero-copy access"
-echo "   • 8-byte unrolled loops for cache optimization"
-echo "   • Branchless arithmetic for maximum speed"
-echo "   • Parallel processing for multiple files"
-echo "   • 2MB I/O buffers for maximum throughput"
-echo
-echo -e "${GREEN}🦀💨 BLAZING FAST RUST PERFORMANCE! 🦀💨${NC}" 
\ No newline at end of file
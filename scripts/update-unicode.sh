@@ -0,0 +1,24 @@
+#!/bin/bash
+
+# 🌐 Refresh the Unicode data files that build.rs turns into lookup tables
+#
+# Usage: scripts/update-unicode.sh [VERSION]   (default: latest)
+# The build itself never touches the network; run this, rebuild, and commit.
+
+set -e
+
+VERSION="${1:-latest}"
+BASE="https://www.unicode.org/Public/$VERSION/ucd"
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+OUTPUT="$SCRIPT_DIR/../unicode"
+
+fetch() {
+    echo "Fetching $1..."
+    curl -fsSL "$BASE/$1" -o "$OUTPUT/$(basename "$1")"
+}
+
+fetch auxiliary/GraphemeBreakProperty.txt
+fetch emoji/emoji-data.txt
+fetch EastAsianWidth.txt
+
+echo "✅ Unicode data updated in $OUTPUT"
@@ -0,0 +1,134 @@
+// ⚡ Counting from tokio (`async` feature)
+//
+// Reads are awaited on the runtime; counting a buffer is CPU work, so large
+// buffers go to the blocking pool while the next one is being read. Both use
+// the same `Counter` state machine as the synchronous paths.
+
+use crate::{Counter, Counts};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task;
+
+const BUFFER_SIZE: usize = 2 * 1024 * 1024;
+// Below this, handing a buffer to another thread costs more than counting it
+const INLINE_LIMIT: usize = 64 * 1024;
+
+/// Counts everything `reader` yields without blocking the runtime
+pub async fn count_async_read<R: AsyncRead + Unpin>(mut reader: R) -> io::Result<Counts> {
+    let mut counter = Counter::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut spare = None;
+    let mut len = fill(&mut reader, &mut buffer).await?;
+
+    while len >= INLINE_LIMIT {
+        // Count this buffer on the blocking pool while the next one fills
+        let counting = task::spawn_blocking(move || {
+            counter.feed(&buffer[..len]);
+            (counter, buffer)
+        });
+        let mut next = spare.take().unwrap_or_else(|| vec![0u8; BUFFER_SIZE]);
+        len = fill(&mut reader, &mut next).await?;
+        let counted;
+        (counter, counted) = counting.await.map_err(io::Error::other)?;
+        spare = Some(counted);
+        buffer = next;
+    }
+
+    // Only the end of the stream leaves a buffer this short
+    counter.feed(&buffer[..len]);
+    Ok(counter.finish())
+}
+
+/// Counts a file on the blocking pool: reading and counting are both blocking
+/// work, so one task does both instead of hopping between threads
+pub async fn count_file_async(path: impl AsRef<Path>) -> io::Result<Counts> {
+    let path = path.as_ref().to_owned();
+    task::spawn_blocking(move || {
+        let mut file = File::open(path)?;
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+        let mut counter = Counter::new();
+        loop {
+            match file.read(&mut buffer)? {
+                0 => return Ok(counter.finish()),
+                bytes_read => counter.feed(&buffer[..bytes_read]),
+            }
+        }
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+// Reads until `buffer` is full or the stream ends, so small reads from
+// sockets and pipes are counted in large batches
+async fn fill<R: AsyncRead + Unpin>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]).await? {
+            0 => break,
+            bytes_read => len += bytes_read,
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_bytes_blazing_speed;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_count_async_read() {
+        assert_eq!(
+            block_on(count_async_read(&b""[..])).unwrap(),
+            Counts::default()
+        );
+        let small = b"hello async\nworld\n";
+        assert_eq!(
+            block_on(count_async_read(&small[..])).unwrap(),
+            count_bytes_blazing_speed(small)
+        );
+
+        // Several offloaded buffers, with words straddling their edges
+        let large = "tokio counts words ".repeat(BUFFER_SIZE / 7);
+        assert_eq!(
+            block_on(count_async_read(large.as_bytes())).unwrap(),
+            count_bytes_blazing_speed(large.as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_small_reads_are_batched() {
+        // A reader that hands out a few bytes at a time, like a slow socket
+        let text = "one two three\n".repeat(10_000);
+        let (mut writer, reader) = tokio::io::duplex(7);
+        let counts = block_on(async {
+            let expected = text.clone();
+            let writing = tokio::spawn(async move {
+                tokio::io::AsyncWriteExt::write_all(&mut writer, expected.as_bytes()).await
+            });
+            let counts = count_async_read(reader).await;
+            writing.await.unwrap().unwrap();
+            counts
+        });
+        assert_eq!(counts.unwrap(), count_bytes_blazing_speed(text.as_bytes()));
+    }
+
+    #[test]
+    fn test_count_file_async() {
+        let path = std::env::temp_dir().join(format!("wc-async-{}.txt", std::process::id()));
+        std::fs::write(&path, "async file\ncounter\n").unwrap();
+        let counts = block_on(count_file_async(&path)).unwrap();
+        assert_eq!((counts.lines, counts.words, counts.chars), (2, 3, 19));
+        std::fs::remove_file(&path).unwrap();
+        assert!(block_on(count_file_async(&path)).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+// 🏁 `wc bench`: throughput of every kernel and I/O strategy on synthetic corpora
+//
+// Corpora are generated in memory from a fixed seed, so runs on different
+// machines measure the same bytes. The I/O strategies read a temporary copy
+// that is already in the page cache: this measures the counting path, not the
+// disk. Every run's counts are checked against the plain kernel.
+
+use crate::reader::IoStrategy;
+use crate::{count_file, Config};
+use std::hint::black_box;
+use std::time::Instant;
+use ultra_blazing_wc::{count_bytes_blazing_speed, Counter, Counts};
+
+pub const USAGE: &str = "wc bench [--size=SIZE] [--trials=N] [--warmup=N] [--json]";
+
+const CORPORA: [&str; 5] = ["ascii", "utf8", "long-lines", "whitespace", "binary"];
+// Chunk size for the streaming `Counter` run, like a typical pipe reader
+const STREAM_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    size: usize,
+    trials: usize,
+    warmup: usize,
+    json: bool,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = Options {
+            size: 64 * 1024 * 1024,
+            trials: 10,
+            warmup: 2,
+            json: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(long) = arg.strip_prefix("--") else {
+                return Err(format!("Unexpected bench argument: {arg}"));
+            };
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            let mut value = || {
+                inline_value
+                    .or_else(|| args.next().map(String::as_str))
+                    .ok_or_else(|| format!("Option --{name} requires a value"))
+            };
+            let number = |value: &str| {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid --{name} value: {value}"))
+            };
+
+            match name {
+                "size" => options.size = parse_size(value()?)?,
+                "trials" => options.trials = number(value()?)?.max(1),
+                "warmup" => options.warmup = number(value()?)?,
+                "json" => options.json = true,
+                _ => return Err(format!("Unknown bench option: --{name}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+// Bytes, optionally with a K, M or G (binary) suffix
+fn parse_size(size: &str) -> Result<usize, String> {
+    let (digits, multiplier) = match size.char_indices().last() {
+        Some((at, 'k' | 'K')) => (&size[..at], 1 << 10),
+        Some((at, 'm' | 'M')) => (&size[..at], 1 << 20),
+        Some((at, 'g' | 'G')) => (&size[..at], 1 << 30),
+        _ => (size, 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .filter(|&n| n > 0)
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid --size value: {size}"))
+}
+
+// xorshift64: reproducible and fast enough to generate gigabytes
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn generate(corpus: &str, size: usize) -> Vec<u8> {
+    const LETTERS: &[u8] = b"etaoinshrdlucmfwypvbgkjqxz";
+    const UTF8: [&str; 8] = ["é", "ß", "ж", "λ", "中", "文", "한", "😀"];
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut data = Vec::with_capacity(size + 64);
+    let mut words_on_line = 0;
+    while data.len() < size {
+        match corpus {
+            "ascii" | "long-lines" => {
+                for _ in 0..1 + rng.below(10) {
+                    data.push(LETTERS[rng.below(LETTERS.len())]);
+                }
+            }
+            "utf8" => {
+                for _ in 0..1 + rng.below(6) {
+                    data.extend_from_slice(UTF8[rng.below(UTF8.len())].as_bytes());
+                }
+            }
+            "whitespace" => data.push(b" \t\n\r"[rng.below(4)]),
+            _ => data.extend_from_slice(&rng.next().to_le_bytes()),
+        }
+        if matches!(corpus, "ascii" | "utf8" | "long-lines") {
+            words_on_line += 1;
+            // Long lines run for about a megabyte before breaking
+            let line_words = if corpus == "long-lines" { 200_000 } else { 12 };
+            if words_on_line >= line_words {
+                data.push(b'\n');
+                words_on_line = 0;
+            } else {
+                data.push(b' ');
+            }
+        }
+    }
+    data.truncate(size);
+    data
+}
+
+#[derive(Debug, PartialEq)]
+struct Summary {
+    mean: f64,
+    // Half-width of the 95% confidence interval of the mean
+    ci95: f64,
+    min: f64,
+    max: f64,
+}
+
+fn summarise(samples: &[f64]) -> Summary {
+    // Two-sided 95% Student's t quantiles for 1..=30 degrees of freedom
+    const T95: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+
+    let n = samples.len();
+    let mean = samples.iter().sum::<f64>() / n as f64;
+    let ci95 = if n > 1 {
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let t = T95.get(n - 2).copied().unwrap_or(1.960);
+        t * (variance / n as f64).sqrt()
+    } else {
+        0.0
+    };
+    Summary {
+        mean,
+        ci95,
+        min: samples.iter().copied().fold(f64::INFINITY, f64::min),
+        max: samples.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+// Deletes the on-disk copy of a corpus however the run ends
+struct TempCorpus(std::path::PathBuf);
+
+impl Drop for TempCorpus {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Runs `count` `warmup + trials` times; GB/s of each trial
+fn measure(
+    options: &Options,
+    bytes: usize,
+    expected: Counts,
+    name: &str,
+    mut count: impl FnMut() -> Result<Counts, String>,
+) -> Result<Vec<f64>, String> {
+    let mut samples = Vec::with_capacity(options.trials);
+    for trial in 0..options.warmup + options.trials {
+        let started = Instant::now();
+        let counts = black_box(count()?);
+        let seconds = started.elapsed().as_secs_f64();
+        if counts != expected {
+            return Err(format!(
+                "{name} miscounted: {counts:?}, the kernel counted {expected:?}"
+            ));
+        }
+        if trial >= options.warmup {
+            samples.push(bytes as f64 / seconds.max(1e-9) / 1e9);
+        }
+    }
+    Ok(samples)
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+
+    let mut strategies = vec![IoStrategy::Mmap, IoStrategy::Read];
+    if cfg!(unix) {
+        strategies.push(IoStrategy::Direct);
+    }
+    if cfg!(target_os = "linux") {
+        strategies.push(IoStrategy::Uring);
+    }
+
+    let mut results = Vec::new();
+    for corpus in CORPORA {
+        let data = generate(corpus, options.size);
+        let expected = count_bytes_blazing_speed(&data);
+
+        let samples = measure(&options, data.len(), expected, "kernel", || {
+            Ok(count_bytes_blazing_speed(black_box(&data)))
+        })?;
+        results.push((corpus, "kernel", summarise(&samples)));
+
+        let samples = measure(&options, data.len(), expected, "stream", || {
+            let mut counter = Counter::new();
+            for chunk in black_box(&data).chunks(STREAM_CHUNK) {
+                counter.feed(chunk);
+            }
+            Ok(counter.finish())
+        })?;
+        results.push((corpus, "stream", summarise(&samples)));
+
+        let path = std::env::temp_dir().join(format!("wc-bench-{}-{corpus}", std::process::id()));
+        let temp = TempCorpus(path);
+        std::fs::write(&temp.0, &data).map_err(|e| format!("{}: {e}", temp.0.display()))?;
+        let path = temp.0.to_string_lossy().into_owned();
+        for &io in &strategies {
+            let config = Config {
+                io,
+                files: vec![path.clone()],
+                ..Config::default()
+            };
+            let samples = measure(&options, data.len(), expected, io.name(), || {
+                let counts = count_file(&path, &config).map_err(|e| format!("{path}: {e}"))?;
+                Ok(Counts {
+                    lines: counts.lines,
+                    words: counts.words,
+                    chars: counts.chars,
+                })
+            })?;
+            results.push((corpus, io.name(), summarise(&samples)));
+        }
+    }
+
+    if options.json {
+        println!("{}", to_json(&options, &results));
+    } else {
+        println!(
+            "wc bench: {:.1} MiB per corpus, {} trials after {} warmup runs",
+            options.size as f64 / (1 << 20) as f64,
+            options.trials,
+            options.warmup
+        );
+        println!(
+            "{:<12} {:<8} {:>8} {:>8} {:>8} {:>8}",
+            "corpus", "bench", "GB/s", "±95%", "min", "max"
+        );
+        for (corpus, bench, summary) in &results {
+            println!(
+                "{corpus:<12} {bench:<8} {:8.3} {:8.3} {:8.3} {:8.3}",
+                summary.mean, summary.ci95, summary.min, summary.max
+            );
+        }
+    }
+    Ok(())
+}
+
+// Corpus and bench names are plain identifiers, so nothing needs escaping
+fn to_json(options: &Options, results: &[(&str, &str, Summary)]) -> String {
+    let results: Vec<String> = results
+        .iter()
+        .map(|(corpus, bench, summary)| {
+            format!(
+                "{{\"corpus\":\"{corpus}\",\"bench\":\"{bench}\",\"gbps\":{:.4},\"ci95\":{:.4},\"min\":{:.4},\"max\":{:.4}}}",
+                summary.mean, summary.ci95, summary.min, summary.max
+            )
+        })
+        .collect();
+    format!(
+        "{{\"size\":{},\"trials\":{},\"warmup\":{},\"results\":[{}]}}",
+        options.size,
+        options.trials,
+        options.warmup,
+        results.join(",")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let args: Vec<String> = ["--size=4K", "--trials", "3", "--json"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let options = Options::parse(&args).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                size: 4096,
+                trials: 3,
+                warmup: 2,
+                json: true
+            }
+        );
+        assert_eq!(parse_size("2m"), Ok(2 << 20));
+        assert!(parse_size("0").is_err() && parse_size("M").is_err());
+        // Too big for usize once multiplied out
+        assert!(parse_size("99999999999G").is_err());
+        assert!(parse_size(&format!("{}G", usize::MAX >> 29)).is_err());
+        assert!(Options::parse(&["ascii".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_corpora_shapes() {
+        let size = 1 << 20;
+        let counts = |corpus| {
+            let data = generate(corpus, size);
+            assert_eq!(data.len(), size);
+            assert_eq!(data, generate(corpus, size), "{corpus} is not reproducible");
+            count_bytes_blazing_speed(&data)
+        };
+        assert!(counts("ascii").lines > 10_000);
+        assert!(counts("long-lines").lines <= 1);
+        assert_eq!(counts("whitespace").words, 0);
+        let text = String::from_utf8_lossy(&generate("utf8", 4096)).into_owned();
+        assert!(text.chars().filter(|c| !c.is_ascii()).count() > 1000);
+        assert!(generate("binary", 4096).contains(&0));
+    }
+
+    #[test]
+    fn test_confidence_interval() {
+        let summary = summarise(&[1.0, 2.0, 3.0]);
+        assert_eq!(summary.mean, 2.0);
+        // t(2) * s / sqrt(n) = 4.303 * 1 / sqrt(3)
+        assert!((summary.ci95 - 2.484).abs() < 1e-3, "{summary:?}");
+        assert_eq!((summary.min, summary.max), (1.0, 3.0));
+        assert_eq!(summarise(&[5.0]).ci95, 0.0);
+    }
+}
@@ -0,0 +1,183 @@
+// 🧱 Binary file detection (`--binary=count|skip|warn`)
+//
+// Only the first chunk is sniffed: known magic numbers first, then NUL bytes
+// and the share of invalid UTF-8. Input that decodes as UTF-16/UTF-32 (by BOM
+// or `--encoding`) is full of NULs and is judged by its magic number alone.
+
+use crate::encoding::Encoding;
+use crate::matcher::memchr;
+use crate::utf8::Utf8Validator;
+
+pub const SNIFF_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryPolicy {
+    Count,
+    Skip,
+    Warn,
+}
+
+impl BinaryPolicy {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "count" => Ok(BinaryPolicy::Count),
+            "skip" => Ok(BinaryPolicy::Skip),
+            "warn" => Ok(BinaryPolicy::Warn),
+            _ => Err(format!(
+                "Unknown binary policy: {name} (expected count, skip, or warn)"
+            )),
+        }
+    }
+}
+
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x7FELF", "ELF executable"),
+    (b"\xFE\xED\xFA\xCE", "Mach-O executable"),
+    (b"\xFE\xED\xFA\xCF", "Mach-O executable"),
+    (b"\xCE\xFA\xED\xFE", "Mach-O executable"),
+    (b"\xCF\xFA\xED\xFE", "Mach-O executable"),
+    (b"\xCA\xFE\xBA\xBE", "Java class or universal binary"),
+    (b"\0asm", "WebAssembly module"),
+    (b"\x89PNG\r\n\x1A\n", "PNG image"),
+    (b"\xFF\xD8\xFF", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"II*\0", "TIFF image"),
+    (b"MM\0*", "TIFF image"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1F\x8B", "gzip archive"),
+    (b"\xFD7zXZ\0", "xz archive"),
+    (b"\x28\xB5\x2F\xFD", "zstd archive"),
+    (b"7z\xBC\xAF\x27\x1C", "7-Zip archive"),
+    (b"SQLite format 3\0", "SQLite database"),
+];
+
+// Signatures made of plain letters also open ordinary prose ("RIFF notes",
+// "ID3 tags"), so these only count with the version or header bytes behind them
+fn ascii_magic(prefix: &[u8]) -> Option<&'static str> {
+    match prefix {
+        [b'R', b'I', b'F', b'F', _, _, _, _, form @ ..]
+            if [&b"WAVE"[..], b"AVI ", b"WEBP"]
+                .iter()
+                .any(|kind| form.starts_with(kind)) =>
+        {
+            Some("RIFF media")
+        }
+        [b'O', b'g', b'g', b'S', 0, ..] => Some("Ogg media"),
+        // The first metadata block is STREAMINFO, possibly flagged as the last
+        [b'f', b'L', b'a', b'C', 0x00 | 0x80, ..] => Some("FLAC audio"),
+        [b'I', b'D', b'3', 2..=4, 0, ..] => Some("MP3 audio"),
+        // A block header, or the end-of-stream marker of an empty archive
+        [b'B', b'Z', b'h', b'1'..=b'9', rest @ ..]
+            if rest.starts_with(b"\x31\x41\x59\x26\x53\x59")
+                || rest.starts_with(b"\x17\x72\x45\x38\x50\x90") =>
+        {
+            Some("bzip2 archive")
+        }
+        [b'w', b'O', b'F', b'F' | b'2', flavor @ ..]
+            if [&b"\0\x01\0\0"[..], b"OTTO", b"true"]
+                .iter()
+                .any(|kind| flavor.starts_with(kind)) =>
+        {
+            Some(if prefix[3] == b'2' {
+                "WOFF2 font"
+            } else {
+                "WOFF font"
+            })
+        }
+        _ => None,
+    }
+}
+
+// What kind of binary `prefix` (the start of a file) looks like, if any
+pub fn detect(prefix: &[u8], encoding: Encoding) -> Option<&'static str> {
+    if let Some(&(_, kind)) = MAGIC.iter().find(|(magic, _)| prefix.starts_with(magic)) {
+        return Some(kind);
+    }
+    if let Some(kind) = ascii_magic(prefix) {
+        return Some(kind);
+    }
+    if prefix.get(257..262) == Some(b"ustar") {
+        return Some("tar archive");
+    }
+
+    if encoding.resolve(prefix).0 != Encoding::Utf8 {
+        return None;
+    }
+    if memchr(0, prefix).is_some() {
+        return Some("NUL bytes");
+    }
+    let mut validator = Utf8Validator::new();
+    validator.feed(prefix);
+    // Even accent-heavy Latin-1 stays well below three errors in ten bytes
+    (validator.finish().invalid * 10 > prefix.len() as u64 * 3).then_some("invalid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_magic_numbers() {
+        assert_eq!(
+            detect(b"\x89PNG\r\n\x1A\n\0\0\0\rIHDR", Encoding::Auto),
+            Some("PNG image")
+        );
+        assert_eq!(
+            detect(b"\x7FELF\x02\x01\x01", Encoding::Auto),
+            Some("ELF executable")
+        );
+        let mut tar = vec![b'a'; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(detect(&tar, Encoding::Auto), Some("tar archive"));
+    }
+
+    #[test]
+    fn test_ascii_magic_needs_header_bytes() {
+        for (header, kind) in [
+            (&b"RIFF\x24\x08\0\0WAVEfmt "[..], "RIFF media"),
+            (b"OggS\0\x02\0\0", "Ogg media"),
+            (b"fLaC\0\0\0\x22", "FLAC audio"),
+            (b"ID3\x04\0\0\0\0", "MP3 audio"),
+            (b"BZh91AY&SY\x8a", "bzip2 archive"),
+            (b"wOF2OTTO\0\0", "WOFF2 font"),
+        ] {
+            assert_eq!(detect(header, Encoding::Auto), Some(kind));
+        }
+        for text in [
+            "RIFF notes: chunk layout for the parser\n",
+            "OggS pages are described below\n",
+            "fLaC is the FLAC stream marker\n",
+            "ID3 tags and how to strip them\n",
+            "BZh9 is the bzip2 header for level nine\n",
+            "wOFF and wOF2 are web font wrappers\n",
+        ] {
+            assert_eq!(detect(text.as_bytes(), Encoding::Auto), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_text_is_not_binary() {
+        assert_eq!(detect(b"", Encoding::Auto), None);
+        assert_eq!(
+            detect("plain text, héllo 🦀\n".as_bytes(), Encoding::Auto),
+            None
+        );
+        // A few Latin-1 accents are not enough to call it binary
+        assert_eq!(
+            detect(b"caf\xe9 au lait, cr\xe8me br\xfbl\xe9e\n", Encoding::Auto),
+            None
+        );
+        // UTF-16 is full of NULs, but it is still text
+        assert_eq!(detect(b"\xFF\xFEh\0i\0\n\0", Encoding::Auto), None);
+    }
+
+    #[test]
+    fn test_nul_bytes_and_invalid_utf8() {
+        assert_eq!(detect(b"abc\0def", Encoding::Auto), Some("NUL bytes"));
+        let noise: Vec<u8> = (0..256u32).map(|i| (i * 167 % 128 + 128) as u8).collect();
+        assert_eq!(detect(&noise, Encoding::Auto), Some("invalid UTF-8"));
+        assert_eq!(detect(&noise, Encoding::Latin1), None);
+    }
+}
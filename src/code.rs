@@ -0,0 +1,520 @@
+// 📐 Source-code aware line classification (code / comment / blank)
+use std::path::Path;
+
+#[derive(Debug)]
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    escapes: bool,
+    multiline: bool,
+    // Triple-quoted strings that open a statement are docstrings (Python)
+    docstring: bool,
+}
+
+const fn quote(delimiter: &'static str, escapes: bool, multiline: bool) -> Quote {
+    Quote {
+        open: delimiter,
+        close: delimiter,
+        escapes,
+        multiline,
+        docstring: false,
+    }
+}
+
+const fn docstring(delimiter: &'static str) -> Quote {
+    Quote {
+        open: delimiter,
+        close: delimiter,
+        escapes: true,
+        multiline: true,
+        docstring: true,
+    }
+}
+
+#[derive(Debug)]
+pub struct Language {
+    pub name: &'static str,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    nested_blocks: bool,
+    // Longer delimiters must come first so `"""` wins over `"`
+    quotes: &'static [Quote],
+    // `#` only starts a comment at the beginning of a word (`$#`, `a#b` are code)
+    comment_at_word_start: bool,
+    // Rust: `r#"..."#` raw strings and `'a'` char literals vs `'a` lifetimes
+    rust_literals: bool,
+}
+
+const C_QUOTES: &[Quote] = &[quote("\"", true, false), quote("'", true, false)];
+
+static RUST: Language = Language {
+    name: "Rust",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: true,
+    quotes: &[quote("\"", true, true)],
+    comment_at_word_start: false,
+    rust_literals: true,
+};
+
+static C: Language = Language {
+    name: "C",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: C_QUOTES,
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static CPP: Language = Language { name: "C++", ..C };
+
+static PYTHON: Language = Language {
+    name: "Python",
+    line_comments: &["#"],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[
+        docstring("\"\"\""),
+        docstring("'''"),
+        quote("\"", true, false),
+        quote("'", true, false),
+    ],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static JAVASCRIPT: Language = Language {
+    name: "JavaScript",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: &[
+        quote("\"", true, false),
+        quote("'", true, false),
+        quote("`", true, true),
+    ],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static TYPESCRIPT: Language = Language {
+    name: "TypeScript",
+    ..JAVASCRIPT
+};
+
+static GO: Language = Language {
+    name: "Go",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: &[
+        quote("\"", true, false),
+        quote("'", true, false),
+        quote("`", false, true),
+    ],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static JAVA: Language = Language {
+    name: "Java",
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: false,
+    quotes: &[
+        quote("\"\"\"", true, true),
+        quote("\"", true, false),
+        quote("'", true, false),
+    ],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static SHELL: Language = Language {
+    name: "Shell",
+    line_comments: &["#"],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[quote("\"", true, true), quote("'", false, true)],
+    comment_at_word_start: true,
+    rust_literals: false,
+};
+
+static TOML: Language = Language {
+    name: "TOML",
+    line_comments: &["#"],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[
+        quote("\"\"\"", true, true),
+        quote("'''", false, true),
+        quote("\"", true, false),
+        quote("'", false, false),
+    ],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+static YAML: Language = Language {
+    name: "YAML",
+    line_comments: &["#"],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[quote("\"", true, true), quote("'", false, true)],
+    comment_at_word_start: true,
+    rust_literals: false,
+};
+
+// Files we cannot identify: every non-blank line is code
+static TEXT: Language = Language {
+    name: "Text",
+    line_comments: &[],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[],
+    comment_at_word_start: false,
+    rust_literals: false,
+};
+
+pub fn language_for_path(path: &str) -> Option<&'static Language> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "rs" => &RUST,
+        "c" | "h" => &C,
+        "cc" | "cpp" | "cxx" | "c++" | "hh" | "hpp" | "hxx" | "h++" => &CPP,
+        "py" | "pyi" | "pyw" => &PYTHON,
+        "js" | "mjs" | "cjs" | "jsx" => &JAVASCRIPT,
+        "ts" | "mts" | "cts" | "tsx" => &TYPESCRIPT,
+        "go" => &GO,
+        "java" => &JAVA,
+        "sh" | "bash" | "zsh" | "ksh" => &SHELL,
+        "toml" => &TOML,
+        "yaml" | "yml" => &YAML,
+        _ => return None,
+    };
+    Some(language)
+}
+
+fn language_for_shebang(first_line: &[u8]) -> Option<&'static Language> {
+    let interpreter_line = first_line.strip_prefix(b"#!")?;
+    let interpreter_line = std::str::from_utf8(interpreter_line).ok()?;
+    let mut words = interpreter_line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    // `#!/usr/bin/env python3` names the real interpreter in the next word
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match program {
+        "python" | "pypy" => Some(&PYTHON),
+        "node" | "nodejs" | "deno" | "bun" => Some(&JAVASCRIPT),
+        "sh" | "bash" | "dash" | "zsh" | "ksh" | "ash" => Some(&SHELL),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CodeCounts {
+    pub language: Option<&'static str>,
+    pub code: u64,
+    pub comments: u64,
+    pub blanks: u64,
+}
+
+impl CodeCounts {
+    #[inline(always)]
+    pub fn add(&mut self, other: &CodeCounts) {
+        self.code += other.code;
+        self.comments += other.comments;
+        self.blanks += other.blanks;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Code,
+    BlockComment { depth: u32 },
+    Str { quote: usize, docstring: bool },
+    RawStr { hashes: usize },
+}
+
+// Streaming classifier: complete lines are scanned as they arrive, only the
+// trailing partial line is buffered between chunks
+pub struct CodeCounter {
+    language: Option<&'static Language>,
+    state: State,
+    partial: Vec<u8>,
+    first_line: bool,
+    counts: CodeCounts,
+}
+
+impl CodeCounter {
+    pub fn new(path: Option<&str>) -> Self {
+        CodeCounter {
+            language: path.and_then(language_for_path),
+            state: State::Code,
+            partial: Vec::new(),
+            first_line: true,
+            counts: CodeCounts::default(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut rest = chunk;
+        while let Some(pos) = rest.iter().position(|&b| b == b'\n') {
+            if self.partial.is_empty() {
+                self.classify(&rest[..pos]);
+            } else {
+                let mut line = std::mem::take(&mut self.partial);
+                line.extend_from_slice(&rest[..pos]);
+                self.classify(&line);
+                line.clear();
+                self.partial = line;
+            }
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+    }
+
+    pub fn finish(mut self) -> CodeCounts {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.classify(&line);
+        }
+        self.counts.language = Some(self.language.unwrap_or(&TEXT).name);
+        self.counts
+    }
+
+    fn classify(&mut self, line: &[u8]) {
+        if self.first_line {
+            self.first_line = false;
+            if self.language.is_none() {
+                self.language = language_for_shebang(line);
+            }
+        }
+        let language = self.language.unwrap_or(&TEXT);
+
+        let (has_code, has_comment) = scan_line(language, &mut self.state, line);
+        if has_code {
+            self.counts.code += 1;
+        } else if has_comment {
+            self.counts.comments += 1;
+        } else {
+            self.counts.blanks += 1;
+        }
+    }
+}
+
+#[inline(always)]
+fn is_identifier_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+// Returns (has_code, has_comment) and leaves `state` as it is at the end of the line
+fn scan_line(language: &Language, state: &mut State, line: &[u8]) -> (bool, bool) {
+    let mut has_code = matches!(
+        *state,
+        State::Str {
+            docstring: false,
+            ..
+        } | State::RawStr { .. }
+    );
+    let mut has_comment = false;
+    let mut i = 0;
+
+    while i < line.len() {
+        let rest = &line[i..];
+        match *state {
+            State::BlockComment { depth } => {
+                let (open, close) = language.block_comment.unwrap();
+                if rest.starts_with(close.as_bytes()) {
+                    i += close.len();
+                    *state = if depth == 1 {
+                        State::Code
+                    } else {
+                        State::BlockComment { depth: depth - 1 }
+                    };
+                } else if language.nested_blocks && rest.starts_with(open.as_bytes()) {
+                    i += open.len();
+                    *state = State::BlockComment { depth: depth + 1 };
+                } else {
+                    i += 1;
+                }
+                has_comment |= !rest[0].is_ascii_whitespace();
+            }
+            State::Str { quote, docstring } => {
+                let delimiter = &language.quotes[quote];
+                if !rest[0].is_ascii_whitespace() {
+                    has_code |= !docstring;
+                    has_comment |= docstring;
+                }
+                if delimiter.escapes && rest[0] == b'\\' {
+                    i += 2;
+                } else if rest.starts_with(delimiter.close.as_bytes()) {
+                    i += delimiter.close.len();
+                    *state = State::Code;
+                } else {
+                    i += 1;
+                }
+            }
+            State::RawStr { hashes } => {
+                if rest[0] == b'"'
+                    && rest.len() > hashes
+                    && rest[1..=hashes].iter().all(|&b| b == b'#')
+                {
+                    i += 1 + hashes;
+                    *state = State::Code;
+                } else {
+                    i += 1;
+                }
+            }
+            State::Code => {
+                let byte = rest[0];
+                if byte.is_ascii_whitespace() {
+                    i += 1;
+                    continue;
+                }
+
+                let at_word_start = i == 0 || line[i - 1].is_ascii_whitespace();
+                if (!language.comment_at_word_start || at_word_start)
+                    && language
+                        .line_comments
+                        .iter()
+                        .any(|marker| rest.starts_with(marker.as_bytes()))
+                {
+                    has_comment = true;
+                    break;
+                }
+
+                if let Some((open, _)) = language.block_comment {
+                    if rest.starts_with(open.as_bytes()) {
+                        i += open.len();
+                        *state = State::BlockComment { depth: 1 };
+                        has_comment = true;
+                        continue;
+                    }
+                }
+
+                if language.rust_literals {
+                    if let Some(consumed) = rust_literal(line, i, state) {
+                        i += consumed;
+                        has_code = true;
+                        continue;
+                    }
+                }
+
+                if let Some((index, delimiter)) = language
+                    .quotes
+                    .iter()
+                    .enumerate()
+                    .find(|(_, quote)| rest.starts_with(quote.open.as_bytes()))
+                {
+                    let docstring = delimiter.docstring && !has_code;
+                    i += delimiter.open.len();
+                    *state = State::Str {
+                        quote: index,
+                        docstring,
+                    };
+                    has_code |= !docstring;
+                    has_comment |= docstring;
+                    continue;
+                }
+
+                has_code = true;
+                i += 1;
+            }
+        }
+    }
+
+    // Single-line string kinds cannot continue past an unescaped newline
+    if let State::Str { quote, .. } = *state {
+        if !language.quotes[quote].multiline && !line.ends_with(b"\\") {
+            *state = State::Code;
+        }
+    }
+
+    (has_code, has_comment)
+}
+
+// Raw strings (`r"…"`, `r#"…"#`) and char literals; a bare `'` is a lifetime
+fn rust_literal(line: &[u8], i: usize, state: &mut State) -> Option<usize> {
+    let rest = &line[i..];
+    match rest[0] {
+        b'r' if i == 0 || !is_identifier_byte(line[i - 1]) || line[i - 1] == b'b' => {
+            let hashes = rest[1..].iter().take_while(|&&b| b == b'#').count();
+            if rest.get(1 + hashes) == Some(&b'"') {
+                *state = State::RawStr { hashes };
+                Some(2 + hashes)
+            } else {
+                None
+            }
+        }
+        b'\'' => {
+            let body = &rest[1..];
+            let literal_len = if body.first() == Some(&b'\\') {
+                body.iter().skip(2).position(|&b| b == b'\'').map(|p| p + 2)
+            } else {
+                let char_len = std::str::from_utf8(&body[..body.len().min(4)])
+                    .or_else(|e| std::str::from_utf8(&body[..e.valid_up_to()]))
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .map_or(1, char::len_utf8);
+                (body.get(char_len) == Some(&b'\'')).then_some(char_len)
+            };
+            // Lifetimes fall through to plain code
+            Some(literal_len.map_or(1, |len| len + 2))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(path: &str, source: &str) -> (u64, u64, u64) {
+        let mut counter = CodeCounter::new(Some(path));
+        counter.feed(source.as_bytes());
+        let counts = counter.finish();
+        (counts.code, counts.comments, counts.blanks)
+    }
+
+    #[test]
+    fn test_rust_nested_comments_and_strings() {
+        let source = "/* outer /* inner */ still comment */\n\
+                      fn main() {\n\
+                      \n\
+                      let s = \"// not a comment\"; // trailing\n\
+                      let r = r#\"/* raw \"# ;\n\
+                      let c = '\"'; fn f<'a>(x: &'a str) {}\n\
+                      // done\n\
+                      }\n";
+        assert_eq!(classify("main.rs", source), (5, 2, 1));
+    }
+
+    #[test]
+    fn test_python_docstrings() {
+        let source = "def f():\n    \"\"\"Docstring\n    # still doc\n    \"\"\"\n    x = \"\"\"not doc\"\"\"\n    # comment\n";
+        assert_eq!(classify("f.py", source), (2, 4, 0));
+    }
+
+    #[test]
+    fn test_shell_shebang_and_word_start_comments() {
+        let mut counter = CodeCounter::new(None);
+        for chunk in b"#!/usr/bin/env bash\necho $# ${#x} # count\n\n# c\n".chunks(3) {
+            counter.feed(chunk);
+        }
+        let counts = counter.finish();
+        assert_eq!(counts.language, Some("Shell"));
+        assert_eq!((counts.code, counts.comments, counts.blanks), (1, 2, 1));
+    }
+
+    #[test]
+    fn test_c_block_comments_do_not_nest() {
+        let source = "/* a /* b */ int x; */\nint y; /* c\n d */\n";
+        // `*/` after `int x;` is code once the first comment closes
+        assert_eq!(classify("x.c", source), (2, 1, 0));
+    }
+}
@@ -0,0 +1,286 @@
+// 🧮 The counting kernel shared by the `wc` binary and the library API
+
+use std::io;
+
+/// Line, word and byte counts (`wc -l`, `-w` and `-c`)
+///
+/// 64-bit even on 32-bit targets: release builds do not check for overflow.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: u64,
+    pub words: u64,
+    /// Bytes, like `wc -c`
+    pub chars: u64,
+}
+
+impl Counts {
+    #[inline(always)]
+    pub fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+    }
+}
+
+/// 🚀 MAXIMUM BLAZING SPEED: lines, words and bytes of one in-memory buffer
+#[inline(always)]
+pub fn count_bytes_blazing_speed(data: &[u8]) -> Counts {
+    let mut lines = 0usize;
+    let mut words = 0usize;
+    let chars = data.len();
+    let mut in_word = false;
+
+    // Process data in the most cache-friendly way possible
+    let mut i = 0;
+    let len = data.len();
+
+    // Unrolled loop for maximum performance
+    while i + 7 < len {
+        let b0 = unsafe { *data.get_unchecked(i) };
+        let b1 = unsafe { *data.get_unchecked(i + 1) };
+        let b2 = unsafe { *data.get_unchecked(i + 2) };
+        let b3 = unsafe { *data.get_unchecked(i + 3) };
+        let b4 = unsafe { *data.get_unchecked(i + 4) };
+        let b5 = unsafe { *data.get_unchecked(i + 5) };
+        let b6 = unsafe { *data.get_unchecked(i + 6) };
+        let b7 = unsafe { *data.get_unchecked(i + 7) };
+
+        // Count newlines with branchless arithmetic
+        lines += (b0 == b'\n') as usize;
+        lines += (b1 == b'\n') as usize;
+        lines += (b2 == b'\n') as usize;
+        lines += (b3 == b'\n') as usize;
+        lines += (b4 == b'\n') as usize;
+        lines += (b5 == b'\n') as usize;
+        lines += (b6 == b'\n') as usize;
+        lines += (b7 == b'\n') as usize;
+
+        // Ultra-fast whitespace detection
+        let ws0 = b0 <= b' ';
+        let ws1 = b1 <= b' ';
+        let ws2 = b2 <= b' ';
+        let ws3 = b3 <= b' ';
+        let ws4 = b4 <= b' ';
+        let ws5 = b5 <= b' ';
+        let ws6 = b6 <= b' ';
+        let ws7 = b7 <= b' ';
+
+        // Word counting with minimal branching
+        if !ws0 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws0 {
+            in_word = false;
+        }
+        if !ws1 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws1 {
+            in_word = false;
+        }
+        if !ws2 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws2 {
+            in_word = false;
+        }
+        if !ws3 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws3 {
+            in_word = false;
+        }
+        if !ws4 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws4 {
+            in_word = false;
+        }
+        if !ws5 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws5 {
+            in_word = false;
+        }
+        if !ws6 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws6 {
+            in_word = false;
+        }
+        if !ws7 && !in_word {
+            words += 1;
+            in_word = true;
+        } else if ws7 {
+            in_word = false;
+        }
+
+        i += 8;
+    }
+
+    // Handle remaining bytes
+    while i < len {
+        let byte = unsafe { *data.get_unchecked(i) };
+
+        if byte == b'\n' {
+            lines += 1;
+        }
+
+        let is_whitespace = byte <= b' ';
+        if !is_whitespace {
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        } else {
+            in_word = false;
+        }
+
+        i += 1;
+    }
+
+    Counts {
+        lines: lines as u64,
+        words: words as u64,
+        chars: chars as u64,
+    }
+}
+
+/// 🧩 Incremental counter for a stream that arrives in chunks of any size
+///
+/// Words split across chunk boundaries are counted once, so feeding a text in
+/// pieces gives the same [`Counts`] as counting it in one go. Only ASCII bytes
+/// separate words, so a UTF-8 character cut in two needs no extra state.
+///
+/// It is also an [`io::Write`] sink that counts whatever is written to it:
+///
+/// ```
+/// use std::io;
+/// use ultra_blazing_wc::Counter;
+///
+/// let mut counter = Counter::new();
+/// io::copy(&mut &b"one two\nthree\n"[..], &mut counter).unwrap();
+/// assert_eq!(counter.snapshot().words, 3);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Counter {
+    counts: Counts,
+    last_was_word_char: bool,
+    first_chunk_seen: bool,
+}
+
+impl Counter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the next piece of the stream
+    #[inline(always)]
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        let mut chunk_counts = count_bytes_blazing_speed(chunk);
+
+        // Handle word boundaries across buffer boundaries
+        if self.first_chunk_seen {
+            let first_byte = unsafe { *chunk.get_unchecked(0) };
+            let first_is_word_char = first_byte > b' ';
+
+            // If the previous chunk ended with a word character and this chunk
+            // starts with a word character, we counted one word too many
+            if self.last_was_word_char && first_is_word_char {
+                chunk_counts.words = chunk_counts.words.saturating_sub(1);
+            }
+        }
+
+        // Remember the last character state for the next iteration
+        let last_byte = unsafe { *chunk.get_unchecked(chunk.len() - 1) };
+        self.last_was_word_char = last_byte > b' ';
+
+        self.counts.add(&chunk_counts);
+        self.first_chunk_seen = true;
+    }
+
+    /// Counts for everything fed so far; feeding may continue
+    pub fn snapshot(&self) -> Counts {
+        self.counts
+    }
+
+    /// Counts for everything fed so far
+    pub fn finish(self) -> Counts {
+        self.counts
+    }
+}
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_matches_whole_buffer() {
+        let text = b"one two\nthree  four\tfive\n six";
+        let expected = count_bytes_blazing_speed(text);
+        assert_eq!(
+            expected,
+            Counts {
+                lines: 2,
+                words: 6,
+                chars: 29
+            }
+        );
+        for split in 0..=text.len() {
+            let mut counter = Counter::new();
+            counter.feed(&text[..split]);
+            counter.feed(&[]);
+            counter.feed(&text[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let text = "héllo wörld\n  ".repeat(10);
+        let mut counter = Counter::new();
+        for byte in text.as_bytes() {
+            counter.feed(std::slice::from_ref(byte));
+        }
+        assert_eq!(counter.finish(), count_bytes_blazing_speed(text.as_bytes()));
+    }
+
+    #[test]
+    fn test_write_and_snapshot() {
+        use std::io::Write;
+
+        let mut counter = Counter::new();
+        write!(counter, "streamed thr").unwrap();
+        assert_eq!(
+            counter.snapshot(),
+            Counts {
+                lines: 0,
+                words: 2,
+                chars: 12
+            }
+        );
+        // The word carries on into the next write
+        counter.write_all(b"ough\nio::copy\n").unwrap();
+        io::copy(&mut &b"  and more"[..], &mut counter).unwrap();
+        counter.flush().unwrap();
+        assert_eq!(
+            counter.finish(),
+            count_bytes_blazing_speed(b"streamed through\nio::copy\n  and more")
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// 🔎 Counts data on its way to someone else's reader
+
+use crate::{Counter, Counts};
+use std::io::{self, BufRead, Read};
+
+/// A [`Read`] (and [`BufRead`]) adapter that passes data through unchanged
+/// while counting it, so sizes come for free without a second pass
+///
+/// ```
+/// use std::io::{BufRead, BufReader};
+/// use ultra_blazing_wc::CountingReader;
+///
+/// let mut reader = CountingReader::new(BufReader::new(&b"first line\nsecond\n"[..]));
+/// let mut line = String::new();
+/// reader.read_line(&mut line).unwrap();
+/// assert_eq!((reader.counts().lines, reader.counts().words), (1, 2));
+/// ```
+#[derive(Debug)]
+pub struct CountingReader<R> {
+    inner: R,
+    counter: Counter,
+}
+
+impl<R> CountingReader<R> {
+    pub fn new(inner: R) -> Self {
+        CountingReader {
+            inner,
+            counter: Counter::new(),
+        }
+    }
+
+    /// Counts for everything read so far
+    pub fn counts(&self) -> Counts {
+        self.counter.snapshot()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Reading through this reference bypasses the count
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.counter.feed(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    // Bytes are counted once consumed, not when they are only peeked at
+    fn consume(&mut self, amt: usize) {
+        // Consuming nothing must not start a read that could block
+        if amt == 0 {
+            return;
+        }
+        // What `fill_buf` just returned is still buffered, so this does no I/O
+        if let Ok(buffered) = self.inner.fill_buf() {
+            self.counter.feed(&buffered[..amt.min(buffered.len())]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::count_bytes_blazing_speed;
+    use std::io::BufReader;
+
+    const TEXT: &[u8] = b"HTTP bodies and\ndecompressed streams\n  counted in passing\nno newline";
+
+    #[test]
+    fn test_read_passes_data_through() {
+        // A tiny buffer splits words across reads
+        let mut reader = CountingReader::new(BufReader::with_capacity(5, TEXT));
+        let mut copied = Vec::new();
+        let mut buf = [0u8; 3];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                n => copied.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert_eq!(copied, TEXT);
+        assert_eq!(reader.counts(), count_bytes_blazing_speed(TEXT));
+        assert_eq!(reader.into_inner().into_inner(), &b""[..]);
+    }
+
+    #[test]
+    fn test_buf_read_counts_consumed_bytes() {
+        let mut reader = CountingReader::new(BufReader::with_capacity(7, TEXT));
+        // Peeking counts nothing
+        assert!(!reader.fill_buf().unwrap().is_empty());
+        assert_eq!(reader.counts(), Counts::default());
+
+        let lines: Vec<String> = (&mut reader).lines().map(Result::unwrap).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(reader.counts(), count_bytes_blazing_speed(TEXT));
+    }
+
+    #[test]
+    fn test_consume_nothing_does_no_io() {
+        // An empty source that fails the test if it is ever read
+        struct NoReads;
+        impl Read for NoReads {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                unreachable!()
+            }
+        }
+        impl BufRead for NoReads {
+            fn fill_buf(&mut self) -> io::Result<&[u8]> {
+                panic!("consume(0) read from the source")
+            }
+            fn consume(&mut self, _amt: usize) {}
+        }
+
+        let mut reader = CountingReader::new(NoReads);
+        reader.consume(0);
+        assert_eq!(reader.counts(), Counts::default());
+    }
+
+    #[test]
+    fn test_running_counts() {
+        let mut reader = CountingReader::new(TEXT);
+        let mut first = [0u8; 16];
+        reader.read_exact(&mut first).unwrap();
+        assert_eq!(reader.counts(), count_bytes_blazing_speed(&TEXT[..16]));
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.counts().chars, TEXT.len() as u64);
+        assert_eq!(reader.counts().words, 10);
+    }
+}
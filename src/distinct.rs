@@ -0,0 +1,326 @@
+// 🧮 Distinct line/word estimation with HyperLogLog sketches
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::Hasher;
+
+pub const MIN_PRECISION: u8 = 4;
+pub const MAX_PRECISION: u8 = 18;
+pub const DEFAULT_PRECISION: u8 = 14;
+
+// HyperLogLog with 2^precision one-byte registers (16KB at the default)
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(MIN_PRECISION, MAX_PRECISION);
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    #[inline(always)]
+    pub fn insert_hash(&mut self, hash: u64) {
+        let p = self.precision as u32;
+        let index = (hash >> (64 - p)) as usize;
+        // The guard bit caps the rank at 64 - p + 1 when the remainder is all zeros
+        let rest = (hash << p) | (1 << (p - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+
+        let register = unsafe { self.registers.get_unchecked_mut(index) };
+        if rank > *register {
+            *register = rank;
+        }
+    }
+
+    // Register-wise max; sketches of different precision are folded down to the coarser one
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.precision < self.precision {
+            *self = self.fold(other.precision);
+        }
+
+        if other.precision == self.precision {
+            for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+                *mine = (*mine).max(*theirs);
+            }
+        } else {
+            let folded = other.fold(self.precision);
+            self.merge(&folded);
+        }
+    }
+
+    fn fold(&self, precision: u8) -> HyperLogLog {
+        let shift = (self.precision - precision) as u32;
+        let mut folded = HyperLogLog::new(precision);
+
+        for (index, &register) in self.registers.iter().enumerate() {
+            if register == 0 {
+                continue;
+            }
+            // The dropped index bits become leading bits of the remainder
+            let dropped = index & ((1 << shift) - 1);
+            let rank = if dropped == 0 {
+                register + shift as u8
+            } else {
+                (shift - (usize::BITS - dropped.leading_zeros())) as u8 + 1
+            };
+            let target = &mut folded.registers[index >> shift];
+            *target = (*target).max(rank);
+        }
+
+        folded
+    }
+
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+
+        let mut sum = 0.0;
+        let mut zeros = 0usize;
+        for &register in &self.registers {
+            sum += 1.0 / (1u64 << register) as f64;
+            zeros += (register == 0) as usize;
+        }
+
+        let raw = alpha * m * m / sum;
+
+        // Linear counting is far more accurate while many registers are still empty
+        if raw <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Distinct {
+    Approx(HyperLogLog),
+    Exact(HashSet<Box<[u8]>>),
+}
+
+impl Distinct {
+    pub fn new(precision: u8, exact: bool) -> Self {
+        if exact {
+            Distinct::Exact(HashSet::new())
+        } else {
+            Distinct::Approx(HyperLogLog::new(precision))
+        }
+    }
+
+    pub fn merge(&mut self, other: &Distinct) {
+        match (self, other) {
+            (Distinct::Approx(mine), Distinct::Approx(theirs)) => mine.merge(theirs),
+            (Distinct::Exact(mine), Distinct::Exact(theirs)) => {
+                mine.extend(theirs.iter().cloned());
+            }
+            (this @ Distinct::Exact(_), Distinct::Approx(theirs)) => {
+                let mut sketch = theirs.clone();
+                if let Distinct::Exact(values) = this {
+                    for value in values.iter() {
+                        sketch.insert_hash(hash_bytes(value));
+                    }
+                }
+                *this = Distinct::Approx(sketch);
+            }
+            (Distinct::Approx(mine), Distinct::Exact(theirs)) => {
+                for value in theirs {
+                    mine.insert_hash(hash_bytes(value));
+                }
+            }
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        match self {
+            Distinct::Approx(sketch) => sketch.estimate().round() as usize,
+            Distinct::Exact(values) => values.len(),
+        }
+    }
+
+    fn insert(&mut self, pending: &mut Pending) {
+        match self {
+            Distinct::Approx(sketch) => sketch.insert_hash(finalize(pending.hasher.finish())),
+            Distinct::Exact(values) => {
+                if !values.contains(pending.buffer.as_slice()) {
+                    values.insert(pending.buffer.as_slice().into());
+                }
+            }
+        }
+        pending.reset();
+    }
+}
+
+// A line or word that may continue into the next chunk
+#[derive(Default)]
+struct Pending {
+    hasher: DefaultHasher,
+    buffer: Vec<u8>,
+    active: bool,
+}
+
+impl Pending {
+    #[inline(always)]
+    fn push(&mut self, bytes: &[u8], exact: bool) {
+        if exact {
+            self.buffer.extend_from_slice(bytes);
+        } else {
+            self.hasher.write(bytes);
+        }
+        self.active = true;
+    }
+
+    fn reset(&mut self) {
+        self.hasher = DefaultHasher::new();
+        self.buffer.clear();
+        self.active = false;
+    }
+}
+
+#[inline(always)]
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    finalize(hasher.finish())
+}
+
+// Murmur3 fmix64 so the register index bits are well mixed
+#[inline(always)]
+fn finalize(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    hash
+}
+
+// Streaming distinct counter that carries partial lines/words across chunks
+pub struct DistinctCounter {
+    lines: Option<Distinct>,
+    words: Option<Distinct>,
+    exact: bool,
+    line: Pending,
+    word: Pending,
+}
+
+impl DistinctCounter {
+    pub fn new(lines: bool, words: bool, precision: u8, exact: bool) -> Self {
+        DistinctCounter {
+            lines: lines.then(|| Distinct::new(precision, exact)),
+            words: words.then(|| Distinct::new(precision, exact)),
+            exact,
+            line: Pending::default(),
+            word: Pending::default(),
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if let Some(lines) = &mut self.lines {
+            let mut rest = chunk;
+            while let Some(pos) = rest.iter().position(|&b| b == b'\n') {
+                self.line.push(&rest[..pos], self.exact);
+                lines.insert(&mut self.line);
+                rest = &rest[pos + 1..];
+            }
+            if !rest.is_empty() {
+                self.line.push(rest, self.exact);
+            }
+        }
+
+        if let Some(words) = &mut self.words {
+            let mut start = 0;
+            for (i, &byte) in chunk.iter().enumerate() {
+                if byte <= b' ' {
+                    if start < i {
+                        self.word.push(&chunk[start..i], self.exact);
+                    }
+                    if self.word.active {
+                        words.insert(&mut self.word);
+                    }
+                    start = i + 1;
+                }
+            }
+            if start < chunk.len() {
+                self.word.push(&chunk[start..], self.exact);
+            }
+        }
+    }
+
+    // Flushes a trailing unterminated line/word and returns the (lines, words) sets
+    pub fn finish(mut self) -> (Option<Distinct>, Option<Distinct>) {
+        if let Some(lines) = &mut self.lines {
+            if self.line.active {
+                lines.insert(&mut self.line);
+            }
+        }
+        if let Some(words) = &mut self.words {
+            if self.word.active {
+                words.insert(&mut self.word);
+            }
+        }
+        (self.lines, self.words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_all(data: &[u8], chunk_size: usize, exact: bool) -> (usize, usize) {
+        let mut counter = DistinctCounter::new(true, true, DEFAULT_PRECISION, exact);
+        for chunk in data.chunks(chunk_size) {
+            counter.feed(chunk);
+        }
+        let (lines, words) = counter.finish();
+        (lines.unwrap().count(), words.unwrap().count())
+    }
+
+    #[test]
+    fn test_exact_counts_survive_chunking() {
+        let data = b"apple banana\napple\n\napple banana\ncherry";
+        for chunk_size in 1..data.len() {
+            // "apple banana", "apple", "", "cherry" / apple, banana, cherry
+            assert_eq!(count_all(data, chunk_size, true), (4, 3));
+        }
+    }
+
+    #[test]
+    fn test_approx_matches_exact_on_small_input() {
+        let data = b"one two three\none two\none two three\nfour";
+        assert_eq!(count_all(data, 5, false), count_all(data, 5, true));
+    }
+
+    #[test]
+    fn test_hyperloglog_error_bound() {
+        let mut sketch = HyperLogLog::new(DEFAULT_PRECISION);
+        for i in 0..200_000u64 {
+            sketch.insert_hash(hash_bytes(&i.to_le_bytes()));
+        }
+        // Standard error at p=14 is ~0.8%; allow a generous 3%
+        let error = (sketch.estimate() - 200_000.0).abs() / 200_000.0;
+        assert!(error < 0.03, "error {error}");
+    }
+
+    #[test]
+    fn test_merge_is_union() {
+        let mut left = HyperLogLog::new(12);
+        let mut right = HyperLogLog::new(10);
+        for i in 0..50_000u64 {
+            left.insert_hash(hash_bytes(&i.to_le_bytes()));
+            right.insert_hash(hash_bytes(&(i + 25_000).to_le_bytes()));
+        }
+        left.merge(&right);
+        let error = (left.estimate() - 75_000.0).abs() / 75_000.0;
+        assert!(error < 0.1, "error {error}");
+    }
+}
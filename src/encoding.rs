@@ -0,0 +1,302 @@
+// 🌐 Input decoding: BOM sniffing and UTF-16/UTF-32/Latin-1 to UTF-8 transcoding
+//
+// Everything downstream (the byte kernel and the extra counters) works on
+// UTF-8, so other encodings are transcoded block by block before counting.
+// Code units and surrogate pairs split across blocks are carried over.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    // Sniff a BOM, otherwise UTF-8
+    Auto,
+    Utf8,
+    // Endianness from the BOM, little-endian without one
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+    Utf32,
+    Utf32Le,
+    Utf32Be,
+    Latin1,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let encoding = match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "auto" => Encoding::Auto,
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "utf-16" | "utf16" => Encoding::Utf16,
+            "utf-16le" | "utf16le" => Encoding::Utf16Le,
+            "utf-16be" | "utf16be" => Encoding::Utf16Be,
+            "utf-32" | "utf32" => Encoding::Utf32,
+            "utf-32le" | "utf32le" => Encoding::Utf32Le,
+            "utf-32be" | "utf32be" => Encoding::Utf32Be,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            _ => {
+                return Err(format!(
+                    "Unknown encoding: {name} (expected auto, utf-8, utf-16, utf-16le, \
+                     utf-16be, utf-32, utf-32le, utf-32be, or latin1)"
+                ))
+            }
+        };
+        Ok(encoding)
+    }
+
+    fn sniff(prefix: &[u8]) -> Option<(Encoding, usize)> {
+        // UTF-32LE's BOM starts with UTF-16LE's, so it must be checked first
+        if prefix.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+            Some((Encoding::Utf32Le, 4))
+        } else if prefix.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+            Some((Encoding::Utf32Be, 4))
+        } else if prefix.starts_with(&[0xFF, 0xFE]) {
+            Some((Encoding::Utf16Le, 2))
+        } else if prefix.starts_with(&[0xFE, 0xFF]) {
+            Some((Encoding::Utf16Be, 2))
+        } else if prefix.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            // Counted as-is like any other UTF-8, matching system `wc`
+            Some((Encoding::Utf8, 0))
+        } else {
+            None
+        }
+    }
+
+    // Concrete encoding for input starting with `prefix`, and the BOM length to skip
+    pub fn resolve(self, prefix: &[u8]) -> (Encoding, usize) {
+        let sniffed = Encoding::sniff(prefix);
+        match self {
+            Encoding::Auto => sniffed.unwrap_or((Encoding::Utf8, 0)),
+            Encoding::Utf16 => match sniffed {
+                Some((encoding @ (Encoding::Utf16Le | Encoding::Utf16Be), bom)) => (encoding, bom),
+                _ => (Encoding::Utf16Le, 0),
+            },
+            Encoding::Utf32 => match sniffed {
+                Some((encoding @ (Encoding::Utf32Le | Encoding::Utf32Be), bom)) => (encoding, bom),
+                _ => (Encoding::Utf32Le, 0),
+            },
+            // An explicit encoding still drops its own BOM
+            explicit => match sniffed {
+                Some((encoding, bom)) if encoding == explicit => (explicit, bom),
+                _ => (explicit, 0),
+            },
+        }
+    }
+
+    fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+            _ => 1,
+        }
+    }
+}
+
+#[inline(always)]
+fn push_char(output: &mut Vec<u8>, c: char) {
+    let mut buffer = [0u8; 4];
+    output.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+}
+
+pub struct Decoder {
+    encoding: Encoding,
+    carry: [u8; 4],
+    carry_len: usize,
+    high_surrogate: Option<u16>,
+}
+
+impl Decoder {
+    // `encoding` must be concrete (see `Encoding::resolve`) and not UTF-8
+    pub fn new(encoding: Encoding) -> Self {
+        debug_assert!(!matches!(
+            encoding,
+            Encoding::Auto | Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32
+        ));
+        Decoder {
+            encoding,
+            carry: [0; 4],
+            carry_len: 0,
+            high_surrogate: None,
+        }
+    }
+
+    pub fn decode(&mut self, mut input: &[u8], output: &mut Vec<u8>) {
+        let unit = self.encoding.unit_len();
+
+        if self.carry_len > 0 {
+            let take = (unit - self.carry_len).min(input.len());
+            self.carry[self.carry_len..self.carry_len + take].copy_from_slice(&input[..take]);
+            self.carry_len += take;
+            input = &input[take..];
+            if self.carry_len < unit {
+                return;
+            }
+            let carried = self.carry;
+            self.carry_len = 0;
+            self.unit(&carried[..unit], output);
+        }
+
+        if self.encoding == Encoding::Latin1 {
+            output.reserve(input.len());
+            for &byte in input {
+                if byte.is_ascii() {
+                    output.push(byte);
+                } else {
+                    push_char(output, byte as char);
+                }
+            }
+            return;
+        }
+
+        output.reserve(input.len() * 3 / unit);
+        let mut units = input.chunks_exact(unit);
+        for bytes in &mut units {
+            self.unit(bytes, output);
+        }
+        let rest = units.remainder();
+        self.carry[..rest.len()].copy_from_slice(rest);
+        self.carry_len = rest.len();
+    }
+
+    // Flushes a dangling code unit or unpaired high surrogate
+    pub fn finish(&mut self, output: &mut Vec<u8>) {
+        if self.high_surrogate.take().is_some() {
+            push_char(output, char::REPLACEMENT_CHARACTER);
+        }
+        if self.carry_len > 0 {
+            self.carry_len = 0;
+            push_char(output, char::REPLACEMENT_CHARACTER);
+        }
+    }
+
+    #[inline(always)]
+    fn unit(&mut self, bytes: &[u8], output: &mut Vec<u8>) {
+        match self.encoding {
+            Encoding::Utf16Le => self.utf16(u16::from_le_bytes([bytes[0], bytes[1]]), output),
+            Encoding::Utf16Be => self.utf16(u16::from_be_bytes([bytes[0], bytes[1]]), output),
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+                let value = if self.encoding == Encoding::Utf32Le {
+                    u32::from_le_bytes(bytes)
+                } else {
+                    u32::from_be_bytes(bytes)
+                };
+                push_char(
+                    output,
+                    char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER),
+                );
+            }
+            _ => push_char(output, bytes[0] as char),
+        }
+    }
+
+    #[inline(always)]
+    fn utf16(&mut self, unit: u16, output: &mut Vec<u8>) {
+        if let Some(high) = self.high_surrogate.take() {
+            if (0xDC00..=0xDFFF).contains(&unit) {
+                let scalar = 0x10000 + (((high - 0xD800) as u32) << 10) + (unit - 0xDC00) as u32;
+                push_char(output, char::from_u32(scalar).unwrap());
+                return;
+            }
+            push_char(output, char::REPLACEMENT_CHARACTER);
+        }
+
+        match unit {
+            0xD800..=0xDBFF => self.high_surrogate = Some(unit),
+            0xDC00..=0xDFFF => push_char(output, char::REPLACEMENT_CHARACTER),
+            _ => push_char(output, char::from_u32(unit as u32).unwrap()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_in_chunks(encoding: Encoding, data: &[u8], chunk_size: usize) -> String {
+        let (encoding, bom) = encoding.resolve(data);
+        let mut decoder = Decoder::new(encoding);
+        let mut output = Vec::new();
+        for chunk in data[bom..].chunks(chunk_size) {
+            decoder.decode(chunk, &mut output);
+        }
+        decoder.finish(&mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    fn utf16le_with_bom(text: &str) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xFE];
+        data.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        data
+    }
+
+    #[test]
+    fn test_unknown_encoding_lists_every_name() {
+        let error = Encoding::parse("utf-7").unwrap_err();
+        let names = error
+            .split_once("(expected ")
+            .unwrap()
+            .1
+            .trim_end_matches(')');
+        let names: Vec<&str> = names
+            .split(", ")
+            .map(|name| name.trim_start_matches("or "))
+            .collect();
+        assert_eq!(names.len(), 9, "{error}");
+        for name in names {
+            assert!(Encoding::parse(name).is_ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_bom_sniffing() {
+        assert_eq!(Encoding::Auto.resolve(b"plain"), (Encoding::Utf8, 0));
+        assert_eq!(
+            Encoding::Auto.resolve(&[0xFF, 0xFE, b'a', 0]),
+            (Encoding::Utf16Le, 2)
+        );
+        assert_eq!(
+            Encoding::Auto.resolve(&[0xFE, 0xFF, 0, b'a']),
+            (Encoding::Utf16Be, 2)
+        );
+        assert_eq!(
+            Encoding::Auto.resolve(&[0xFF, 0xFE, 0, 0]),
+            (Encoding::Utf32Le, 4)
+        );
+        assert_eq!(Encoding::Utf32.resolve(b"abcd"), (Encoding::Utf32Le, 0));
+        assert_eq!(
+            Encoding::Utf16Be.resolve(&[0xFE, 0xFF]),
+            (Encoding::Utf16Be, 2)
+        );
+    }
+
+    #[test]
+    fn test_surrogate_pairs_split_across_chunks() {
+        let text = "héllo 🦀 wörld\r\n𝄞 end";
+        let data = utf16le_with_bom(text);
+        for chunk_size in 1..=8 {
+            assert_eq!(decode_in_chunks(Encoding::Auto, &data, chunk_size), text);
+        }
+
+        let mut data = vec![0x00, 0x00, 0xFE, 0xFF];
+        data.extend(text.chars().flat_map(|c| (c as u32).to_be_bytes()));
+        for chunk_size in 1..=9 {
+            assert_eq!(decode_in_chunks(Encoding::Auto, &data, chunk_size), text);
+        }
+    }
+
+    #[test]
+    fn test_invalid_sequences_become_replacement_characters() {
+        // Lone low surrogate, lone high surrogate, then a dangling odd byte
+        let data = [0xFF, 0xFE, 0x00, 0xDC, b'a', 0, 0x00, 0xD8, b'b', 0, b'c'];
+        assert_eq!(
+            decode_in_chunks(Encoding::Auto, &data, 3),
+            "\u{FFFD}a\u{FFFD}b\u{FFFD}"
+        );
+    }
+
+    #[test]
+    fn test_latin1() {
+        assert_eq!(
+            decode_in_chunks(Encoding::Latin1, b"caf\xe9 \xa0x", 2),
+            "café \u{a0}x"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+// ↩️ Line-ending analysis (`--eol`) and alternative line terminators for `-l`
+use crate::matcher::memchr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    // Every '\n', CRLF included (what `wc -l` has always counted)
+    Lf,
+    Crlf,
+    // Every '\r', CRLF included (classic Mac OS)
+    Cr,
+    // LF, CRLF and lone CR each end one line
+    Any,
+}
+
+impl LineEnding {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            "cr" => Ok(LineEnding::Cr),
+            "any" => Ok(LineEnding::Any),
+            _ => Err(format!(
+                "Unknown line ending: {name} (expected lf, crlf, cr, or any)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct EolCounts {
+    // Bare LF, CRLF pairs and lone CR, each counted once
+    pub lf: u64,
+    pub crlf: u64,
+    pub cr: u64,
+    // Inputs whose last line has no terminator (0 or 1 per file)
+    pub unterminated: u64,
+}
+
+impl EolCounts {
+    #[inline(always)]
+    pub fn add(&mut self, other: &EolCounts) {
+        self.lf += other.lf;
+        self.crlf += other.crlf;
+        self.cr += other.cr;
+        self.unterminated += other.unterminated;
+    }
+
+    pub fn lines(&self, ending: LineEnding) -> u64 {
+        match ending {
+            LineEnding::Lf => self.lf + self.crlf,
+            LineEnding::Crlf => self.crlf,
+            LineEnding::Cr => self.cr + self.crlf,
+            LineEnding::Any => self.lf + self.crlf + self.cr,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EolCounter {
+    newlines: u64,
+    carriage_returns: u64,
+    crlf: u64,
+    // The previous chunk ended in '\r'; a leading '\n' here completes a CRLF
+    pending_cr: bool,
+    last_byte: Option<u8>,
+}
+
+impl EolCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let Some(&last) = chunk.last() else {
+            return;
+        };
+
+        if self.pending_cr && chunk[0] == b'\n' {
+            self.crlf += 1;
+        }
+
+        self.newlines += chunk.iter().filter(|&&b| b == b'\n').count() as u64;
+
+        // '\r' is rare outside CRLF files, so hop between them with memchr
+        let mut pos = 0;
+        while let Some(found) = memchr(b'\r', &chunk[pos..]) {
+            let at = pos + found;
+            self.carriage_returns += 1;
+            if chunk.get(at + 1) == Some(&b'\n') {
+                self.crlf += 1;
+            }
+            pos = at + 1;
+        }
+
+        self.pending_cr = last == b'\r';
+        self.last_byte = Some(last);
+    }
+
+    pub fn finish(self) -> EolCounts {
+        EolCounts {
+            lf: self.newlines - self.crlf,
+            crlf: self.crlf,
+            cr: self.carriage_returns - self.crlf,
+            unterminated: matches!(self.last_byte, Some(b) if b != b'\n' && b != b'\r') as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyze(data: &[u8], chunk_size: usize) -> EolCounts {
+        let mut counter = EolCounter::new();
+        for chunk in data.chunks(chunk_size) {
+            counter.feed(chunk);
+        }
+        counter.finish()
+    }
+
+    #[test]
+    fn test_mixed_endings() {
+        let data = b"unix\nwindows\r\nmac\rmore\r\n\r\rtail";
+        for chunk_size in 1..=data.len() {
+            let counts = analyze(data, chunk_size);
+            assert_eq!(
+                counts,
+                EolCounts {
+                    lf: 1,
+                    crlf: 2,
+                    cr: 3,
+                    unterminated: 1
+                },
+                "chunks of {chunk_size}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_ending_modes() {
+        let counts = analyze(b"a\r\nb\nc\rd\r", 4);
+        assert_eq!(counts.unterminated, 0);
+        assert_eq!(counts.lines(LineEnding::Lf), 2);
+        assert_eq!(counts.lines(LineEnding::Crlf), 1);
+        assert_eq!(counts.lines(LineEnding::Cr), 3);
+        assert_eq!(counts.lines(LineEnding::Any), 4);
+    }
+
+    #[test]
+    fn test_classic_mac_file() {
+        let counts = analyze(b"one\rtwo\rthree\r", 3);
+        assert_eq!(counts.lines(LineEnding::Lf), 0);
+        assert_eq!(counts.lines(LineEnding::Any), 3);
+        assert_eq!(analyze(b"", 1), EolCounts::default());
+    }
+}
@@ -0,0 +1,234 @@
+// 🔤 Grapheme clusters (UAX #29) and terminal display width (UAX #11)
+//
+// Extended grapheme clusters follow rules GB3-GB13; the Indic conjunct rule
+// (GB9c) is not applied, so a virama joins its consonants no further than the
+// legacy rules do. A cluster is as wide as its widest code point: East Asian
+// Wide/Fullwidth and regional indicators take two columns, controls, marks
+// and joiners none, and an emoji presentation selector (U+FE0F) widens the
+// whole cluster to two.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Gcb {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    ExtendedPictographic,
+}
+
+include!(concat!(env!("OUT_DIR"), "/unicode_tables.rs"));
+
+const EMOJI_PRESENTATION: char = '\u{FE0F}';
+
+#[inline(always)]
+fn properties(c: char) -> (Gcb, u8) {
+    let cp = c as u32;
+    // 🚀 ASCII never needs the table
+    if cp < 0x7F {
+        return match c {
+            '\r' => (Gcb::Cr, 0),
+            '\n' => (Gcb::Lf, 0),
+            '\0'..='\x1F' => (Gcb::Control, 0),
+            _ => (Gcb::Other, 1),
+        };
+    }
+    UNICODE_TABLE
+        .binary_search_by(|&(first, last, _, _)| {
+            if last < cp {
+                std::cmp::Ordering::Less
+            } else if first > cp {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .map_or((Gcb::Other, 1), |i| {
+            let (_, _, category, width) = UNICODE_TABLE[i];
+            (category, width)
+        })
+}
+
+// Progress through `ExtPict Extend* ZWJ` for the emoji ZWJ rule (GB11)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Emoji {
+    #[default]
+    None,
+    Pictographic,
+    Joined,
+}
+
+#[derive(Default)]
+pub struct GraphemeCounter {
+    graphemes: u64,
+    width: u64,
+    cluster_width: usize,
+    previous: Option<Gcb>,
+    // An odd number of regional indicators ends the text so far (GB12/GB13)
+    odd_regional: bool,
+    emoji: Emoji,
+    // Partial UTF-8 sequence carried over from the previous chunk
+    utf8: [u8; 4],
+    utf8_len: usize,
+    utf8_need: usize,
+}
+
+impl GraphemeCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if self.utf8_need > 0 {
+                if byte & 0xC0 == 0x80 {
+                    self.utf8[self.utf8_len] = byte;
+                    self.utf8_len += 1;
+                    if self.utf8_len == self.utf8_need {
+                        let c = std::str::from_utf8(&self.utf8[..self.utf8_len])
+                            .ok()
+                            .and_then(|s| s.chars().next())
+                            .unwrap_or(char::REPLACEMENT_CHARACTER);
+                        self.utf8_need = 0;
+                        self.char(c);
+                    }
+                    continue;
+                }
+                // Truncated sequence: one replacement character, like the decoder
+                self.utf8_need = 0;
+                self.char(char::REPLACEMENT_CHARACTER);
+            }
+
+            match byte {
+                0x00..=0x7F => self.char(byte as char),
+                0xC2..=0xF4 => {
+                    self.utf8[0] = byte;
+                    self.utf8_len = 1;
+                    self.utf8_need = match byte {
+                        0xC2..=0xDF => 2,
+                        0xE0..=0xEF => 3,
+                        _ => 4,
+                    };
+                }
+                _ => self.char(char::REPLACEMENT_CHARACTER),
+            }
+        }
+    }
+
+    // Returns (grapheme clusters, display width)
+    pub fn finish(mut self) -> (u64, u64) {
+        if self.utf8_need > 0 {
+            self.char(char::REPLACEMENT_CHARACTER);
+        }
+        (self.graphemes, self.width + self.cluster_width as u64)
+    }
+
+    fn is_boundary(&self, current: Gcb) -> bool {
+        use Gcb::*;
+
+        let Some(previous) = self.previous else {
+            return true;
+        };
+        match (previous, current) {
+            (Cr, Lf) => false,
+            (Control | Cr | Lf, _) | (_, Control | Cr | Lf) => true,
+            (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => false,
+            (_, Extend | Zwj | SpacingMark) | (Prepend, _) => false,
+            (Zwj, ExtendedPictographic) => self.emoji != Emoji::Joined,
+            (RegionalIndicator, RegionalIndicator) => !self.odd_regional,
+            _ => true,
+        }
+    }
+
+    #[inline(always)]
+    fn char(&mut self, c: char) {
+        let (category, width) = properties(c);
+
+        if self.is_boundary(category) {
+            self.graphemes += 1;
+            self.width += self.cluster_width as u64;
+            self.cluster_width = 0;
+        }
+        self.cluster_width = self.cluster_width.max(width as usize);
+        if c == EMOJI_PRESENTATION {
+            self.cluster_width = 2;
+        }
+
+        self.odd_regional = category == Gcb::RegionalIndicator
+            && !(self.previous == Some(Gcb::RegionalIndicator) && self.odd_regional);
+        self.emoji = match (category, self.emoji) {
+            (Gcb::ExtendedPictographic, _) => Emoji::Pictographic,
+            (Gcb::Extend, Emoji::Pictographic) => Emoji::Pictographic,
+            (Gcb::Zwj, Emoji::Pictographic) => Emoji::Joined,
+            _ => Emoji::None,
+        };
+        self.previous = Some(category);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(text: &str) -> (u64, u64) {
+        let mut counter = GraphemeCounter::new();
+        counter.feed(text.as_bytes());
+        counter.finish()
+    }
+
+    #[test]
+    fn test_clusters() {
+        assert_eq!(measure(""), (0, 0));
+        assert_eq!(measure("abc"), (3, 3));
+        assert_eq!(measure("e\u{301}te\u{301}"), (3, 3));
+        assert_eq!(measure("a\r\nb\n"), (4, 2));
+        // Family emoji joined with ZWJ, and a skin-tone modifier
+        assert_eq!(measure("👨‍👩‍👧 👍🏽"), (3, 5));
+        // Flags pair up regional indicators; a ninth one stands alone
+        assert_eq!(measure("🇺🇸🇬🇧🇯🇵🇫🇷🇩"), (5, 10));
+        // Conjoining jamo, Thai SARA AM (a spacing mark) and a prepended mark
+        assert_eq!(measure("\u{1100}\u{1161}\u{11A8}กำ\u{600}1"), (3, 4));
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(measure("日本語テキスト"), (7, 14));
+        assert_eq!(measure("ｆｕｌｌ"), (4, 8));
+        assert_eq!(measure("a\tb\u{200B}c"), (5, 3));
+        // Emoji presentation selector widens a text-style symbol
+        assert_eq!(measure("\u{2764}\u{FE0F}\u{2764}"), (2, 3));
+    }
+
+    #[test]
+    fn test_width_by_east_asian_width() {
+        // Unassigned code points are neutral, so one column each
+        assert_eq!(measure("\u{378}\u{FFEF}\u{11EF9}"), (3, 3));
+        // A letter of a newer script that sits past an old unassigned gap
+        assert_eq!(measure("\u{11F04}"), (1, 1));
+        // Ambiguous characters take the narrow width outside CJK contexts
+        assert_eq!(measure("¡°①"), (3, 3));
+        // Fullwidth forms, including the ideographic space
+        assert_eq!(measure("Ａ￥\u{3000}"), (3, 6));
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let text = "héllo 👩🏽‍💻 wörld\r\n🇨🇦日本\u{1100}\u{1161}e\u{301}\u{FE0F}\n";
+        let expected = measure(text);
+        for split in 0..=text.len() {
+            let mut counter = GraphemeCounter::new();
+            counter.feed(&text.as_bytes()[..split]);
+            counter.feed(&text.as_bytes()[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+    }
+}
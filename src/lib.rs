@@ -0,0 +1,19 @@
+//! 🚀 The counting core of ULTRA BLAZING `wc`, as a library
+//!
+//! [`count_bytes_blazing_speed`] counts a buffer in one go; [`Counter`] counts a
+//! stream fed in chunks of any size (or written to it), and [`CountingReader`]
+//! counts data as it is read through it. With the `async` feature,
+//! [`count_async_read`] and [`count_file_async`] count from tokio without
+//! blocking the runtime.
+
+mod counter;
+mod counting_reader;
+
+pub use counter::{count_bytes_blazing_speed, Counter, Counts};
+pub use counting_reader::CountingReader;
+
+#[cfg(feature = "async")]
+mod async_count;
+
+#[cfg(feature = "async")]
+pub use async_count::{count_async_read, count_file_async};
@@ -1,13 +1,69 @@
+mod bench;
+mod binary;
+mod code;
+mod distinct;
+mod encoding;
+mod eol;
+mod graphemes;
+mod markdown;
+mod matcher;
+mod progress;
+mod prose;
+mod reader;
+#[cfg(unix)]
+mod sigbus;
+mod timings;
+mod tokens;
+#[cfg(target_os = "linux")]
+mod uring;
+mod utf8;
+
+use binary::BinaryPolicy;
+use code::{CodeCounter, CodeCounts};
+use distinct::{Distinct, DistinctCounter};
+use encoding::{Decoder, Encoding};
+use eol::{EolCounter, EolCounts, LineEnding};
+use graphemes::GraphemeCounter;
+use markdown::MarkdownCounter;
+use matcher::{MatchCounter, MatchCounts, Matcher};
+use progress::{Progress, Reporter};
+use prose::ProseCounter;
+use reader::{BufferedReader, ChunkReader, IoStrategy};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
+use timings::Timing;
+use tokens::{TokenCounter, Tokenizer};
+use ultra_blazing_wc::{count_bytes_blazing_speed, Counter, Counts};
+use utf8::{Utf8Report, Utf8Validator};
 
 #[derive(Debug, Default, Clone)]
 struct WcCounts {
-    lines: usize,
-    words: usize,
-    chars: usize,
+    // 64-bit even on 32-bit targets: release builds do not check for overflow
+    lines: u64,
+    words: u64,
+    chars: u64,
+    sentences: u64,
+    paragraphs: u64,
+    graphemes: u64,
+    width: u64,
+    tokens: u64,
+    prose_words: u64,
+    code_words: u64,
+    distinct_lines: Option<Distinct>,
+    distinct_words: Option<Distinct>,
+    matches: Vec<MatchCounts>,
+    utf8: Option<Utf8Report>,
+    eol: Option<EolCounts>,
+    code: Option<CodeCounts>,
+    // What the file looked like if it was detected as binary (never summed)
+    binary: Option<&'static str>,
+    // `--timings`, per file (never summed)
+    timing: Option<Timing>,
 }
 
 impl WcCounts {
@@ -16,14 +72,94 @@ impl WcCounts {
         self.lines += other.lines;
         self.words += other.words;
         self.chars += other.chars;
+        self.sentences += other.sentences;
+        self.paragraphs += other.paragraphs;
+        self.graphemes += other.graphemes;
+        self.width += other.width;
+        self.tokens += other.tokens;
+        self.prose_words += other.prose_words;
+        self.code_words += other.code_words;
+        merge_distinct(&mut self.distinct_lines, &other.distinct_lines);
+        merge_distinct(&mut self.distinct_words, &other.distinct_words);
+        if self.matches.len() < other.matches.len() {
+            self.matches
+                .resize(other.matches.len(), MatchCounts::default());
+        }
+        for (total, other) in self.matches.iter_mut().zip(&other.matches) {
+            total.lines += other.lines;
+            total.matches += other.matches;
+        }
+        // First-error locations are per file, only the counts add up
+        if let Some(other_utf8) = &other.utf8 {
+            self.utf8.get_or_insert_with(Utf8Report::default).invalid += other_utf8.invalid;
+        }
+        if let Some(other_eol) = &other.eol {
+            self.eol
+                .get_or_insert_with(EolCounts::default)
+                .add(other_eol);
+        }
+        if let Some(other_code) = &other.code {
+            self.code
+                .get_or_insert_with(CodeCounts::default)
+                .add(other_code);
+        }
+    }
+}
+
+impl From<Counts> for WcCounts {
+    fn from(counts: Counts) -> Self {
+        WcCounts {
+            lines: counts.lines,
+            words: counts.words,
+            chars: counts.chars,
+            ..WcCounts::default()
+        }
     }
 }
 
-#[derive(Debug)]
+fn merge_distinct(total: &mut Option<Distinct>, other: &Option<Distinct>) {
+    match (total, other) {
+        (Some(total), Some(other)) => total.merge(other),
+        (total @ None, Some(other)) => *total = Some(other.clone()),
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone)]
 struct Config {
     show_lines: bool,
     show_words: bool,
     show_chars: bool,
+    show_sentences: bool,
+    show_paragraphs: bool,
+    show_graphemes: bool,
+    show_width: bool,
+    token_encoding: Option<String>,
+    vocab: Option<String>,
+    tokenizer: Option<Arc<Tokenizer>>,
+    show_markdown: bool,
+    show_distinct_lines: bool,
+    show_distinct_words: bool,
+    distinct_precision: u8,
+    distinct_exact: bool,
+    match_patterns: Vec<String>,
+    match_fixed: bool,
+    match_ignore_case: bool,
+    matchers: Vec<Matcher>,
+    show_code: bool,
+    check_utf8: bool,
+    strict_utf8: bool,
+    show_eol: bool,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    binary: BinaryPolicy,
+    io: IoStrategy,
+    no_cache_pollution: bool,
+    tee: bool,
+    report_to: Option<String>,
+    show_progress: bool,
+    progress: Option<Arc<Progress>>,
+    show_timings: bool,
     files: Vec<String>,
 }
 
@@ -33,6 +169,36 @@ impl Default for Config {
             show_lines: true,
             show_words: true,
             show_chars: true,
+            show_sentences: false,
+            show_paragraphs: false,
+            show_graphemes: false,
+            show_width: false,
+            token_encoding: None,
+            vocab: None,
+            tokenizer: None,
+            show_markdown: false,
+            show_distinct_lines: false,
+            show_distinct_words: false,
+            distinct_precision: distinct::DEFAULT_PRECISION,
+            distinct_exact: false,
+            match_patterns: Vec::new(),
+            match_fixed: false,
+            match_ignore_case: false,
+            matchers: Vec::new(),
+            show_code: false,
+            check_utf8: false,
+            strict_utf8: false,
+            show_eol: false,
+            line_ending: LineEnding::Lf,
+            encoding: Encoding::Auto,
+            binary: BinaryPolicy::Count,
+            io: IoStrategy::Auto,
+            no_cache_pollution: false,
+            tee: false,
+            report_to: None,
+            show_progress: false,
+            progress: None,
+            show_timings: false,
             files: Vec::new(),
         }
     }
@@ -40,7 +206,11 @@ impl Default for Config {
 
 impl Config {
     fn from_args() -> Result<Self, String> {
-        let args: Vec<String> = env::args().collect();
+        Self::parse(env::args().collect())
+    }
+
+    // `args[0]` is the program name, as in `env::args()`
+    fn parse(args: Vec<String>) -> Result<Self, String> {
         let mut config = Config::default();
         let mut explicit_flags = false;
 
@@ -48,19 +218,110 @@ impl Config {
         while i < args.len() {
             let arg = &args[i];
 
-            if arg.starts_with('-') && arg.len() > 1 {
-                if !explicit_flags {
-                    explicit_flags = true;
-                    config.show_lines = false;
-                    config.show_words = false;
-                    config.show_chars = false;
+            if let Some(long) = arg.strip_prefix("--") {
+                let (name, inline_value) = match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                };
+                let mut value = || -> Result<String, String> {
+                    if let Some(value) = inline_value.clone() {
+                        return Ok(value);
+                    }
+                    i += 1;
+                    args.get(i)
+                        .cloned()
+                        .ok_or_else(|| format!("Option --{name} requires a value"))
+                };
+
+                match name {
+                    "sentences" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_sentences = true;
+                    }
+                    "paragraphs" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_paragraphs = true;
+                    }
+                    "graphemes" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_graphemes = true;
+                    }
+                    "width" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_width = true;
+                    }
+                    "tokens" => config.token_encoding = Some(value()?),
+                    "vocab" => config.vocab = Some(value()?),
+                    "markdown" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_markdown = true;
+                    }
+                    "distinct-lines" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_distinct_lines = true;
+                    }
+                    "distinct-words" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_distinct_words = true;
+                    }
+                    "distinct-precision" => {
+                        let value = value()?;
+                        config.distinct_precision = value
+                            .parse()
+                            .ok()
+                            .filter(|p| {
+                                (distinct::MIN_PRECISION..=distinct::MAX_PRECISION).contains(p)
+                            })
+                            .ok_or_else(|| {
+                                format!(
+                                    "Invalid --distinct-precision: {value} (expected {}-{})",
+                                    distinct::MIN_PRECISION,
+                                    distinct::MAX_PRECISION
+                                )
+                            })?;
+                    }
+                    "exact" => config.distinct_exact = true,
+                    "match" => config.match_patterns.push(value()?),
+                    "fixed-strings" => config.match_fixed = true,
+                    "check-utf8" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.check_utf8 = true;
+                    }
+                    "strict-utf8" => config.strict_utf8 = true,
+                    "eol" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_eol = true;
+                    }
+                    "line-ending" => config.line_ending = LineEnding::parse(&value()?)?,
+                    "binary" => config.binary = BinaryPolicy::parse(&value()?)?,
+                    "skip-binary" => config.binary = BinaryPolicy::Skip,
+                    "io" => config.io = IoStrategy::parse(&value()?)?,
+                    "no-cache-pollution" => config.no_cache_pollution = true,
+                    "tee" => config.tee = true,
+                    "report-to" => config.report_to = Some(value()?),
+                    "progress" => config.show_progress = true,
+                    "timings" => config.show_timings = true,
+                    "encoding" => config.encoding = Encoding::parse(&value()?)?,
+                    "ignore-case" => config.match_ignore_case = true,
+                    "code" => {
+                        config.select_counters(&mut explicit_flags);
+                        config.show_code = true;
+                    }
+                    _ => return Err(format!("Unknown option: --{name}")),
                 }
-
+            } else if arg.starts_with('-') && arg.len() > 1 {
                 for ch in arg.chars().skip(1) {
+                    if ch != 'i' && ch != 'F' {
+                        config.select_counters(&mut explicit_flags);
+                    }
                     match ch {
                         'l' => config.show_lines = true,
                         'w' => config.show_words = true,
                         'c' => config.show_chars = true,
+                        's' => config.show_sentences = true,
+                        'p' => config.show_paragraphs = true,
+                        'i' => config.match_ignore_case = true,
+                        'F' => config.match_fixed = true,
                         _ => return Err(format!("Unknown flag: -{ch}")),
                     }
                 }
@@ -70,228 +331,407 @@ impl Config {
             i += 1;
         }
 
+        // `-i`/`-F` may come after the patterns they apply to
+        config.matchers = config
+            .match_patterns
+            .iter()
+            .map(|pattern| Matcher::new(pattern, config.match_fixed, config.match_ignore_case))
+            .collect::<Result<_, _>>()?;
+
+        if config.tee && !config.files.is_empty() {
+            return Err("--tee forwards standard input; it takes no files".to_string());
+        }
+        if config.report_to.is_some() && !config.tee {
+            return Err("--report-to needs --tee (counts otherwise go to stdout)".to_string());
+        }
+
+        // Loaded once here and shared by every file's thread
+        config.tokenizer = match (&config.token_encoding, &config.vocab) {
+            (Some(name), vocab) => Some(Arc::new(Tokenizer::load(name, vocab.as_deref())?)),
+            (None, Some(_)) => {
+                return Err("--vocab needs --tokens=NAME to pick the pre-tokeniser".to_string())
+            }
+            (None, None) => None,
+        };
+
+        // Shared by the worker threads, which report into it as they go
+        config.progress = config
+            .show_progress
+            .then(|| Arc::new(Progress::new(&config.files)));
+
         Ok(config)
     }
-}
 
-// 🚀 MAXIMUM BLAZING SPEED - Optimized for pure performance
-#[inline(always)]
-fn count_bytes_blazing_speed(data: &[u8]) -> WcCounts {
-    let mut lines = 0usize;
-    let mut words = 0usize;
-    let chars = data.len();
-    let mut in_word = false;
-
-    // Process data in the most cache-friendly way possible
-    let mut i = 0;
-    let len = data.len();
-
-    // Unrolled loop for maximum performance
-    while i + 7 < len {
-        let b0 = unsafe { *data.get_unchecked(i) };
-        let b1 = unsafe { *data.get_unchecked(i + 1) };
-        let b2 = unsafe { *data.get_unchecked(i + 2) };
-        let b3 = unsafe { *data.get_unchecked(i + 3) };
-        let b4 = unsafe { *data.get_unchecked(i + 4) };
-        let b5 = unsafe { *data.get_unchecked(i + 5) };
-        let b6 = unsafe { *data.get_unchecked(i + 6) };
-        let b7 = unsafe { *data.get_unchecked(i + 7) };
-
-        // Count newlines with branchless arithmetic
-        lines += (b0 == b'\n') as usize;
-        lines += (b1 == b'\n') as usize;
-        lines += (b2 == b'\n') as usize;
-        lines += (b3 == b'\n') as usize;
-        lines += (b4 == b'\n') as usize;
-        lines += (b5 == b'\n') as usize;
-        lines += (b6 == b'\n') as usize;
-        lines += (b7 == b'\n') as usize;
-
-        // Ultra-fast whitespace detection
-        let ws0 = b0 <= b' ';
-        let ws1 = b1 <= b' ';
-        let ws2 = b2 <= b' ';
-        let ws3 = b3 <= b' ';
-        let ws4 = b4 <= b' ';
-        let ws5 = b5 <= b' ';
-        let ws6 = b6 <= b' ';
-        let ws7 = b7 <= b' ';
-
-        // Word counting with minimal branching
-        if !ws0 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws0 {
-            in_word = false;
-        }
-        if !ws1 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws1 {
-            in_word = false;
-        }
-        if !ws2 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws2 {
-            in_word = false;
-        }
-        if !ws3 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws3 {
-            in_word = false;
-        }
-        if !ws4 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws4 {
-            in_word = false;
-        }
-        if !ws5 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws5 {
-            in_word = false;
-        }
-        if !ws6 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws6 {
-            in_word = false;
-        }
-        if !ws7 && !in_word {
-            words += 1;
-            in_word = true;
-        } else if ws7 {
-            in_word = false;
-        }
-
-        i += 8;
-    }
-
-    // Handle remaining bytes
-    while i < len {
-        let byte = unsafe { *data.get_unchecked(i) };
-
-        if byte == b'\n' {
-            lines += 1;
-        }
-
-        let is_whitespace = byte <= b' ';
-        if !is_whitespace {
-            if !in_word {
-                words += 1;
-                in_word = true;
-            }
-        } else {
-            in_word = false;
+    // The first counter flag replaces the default `-lwc` selection
+    fn select_counters(&mut self, explicit_flags: &mut bool) {
+        if !*explicit_flags {
+            *explicit_flags = true;
+            self.show_lines = false;
+            self.show_words = false;
+            self.show_chars = false;
         }
+    }
 
-        i += 1;
+    fn wants_prose(&self) -> bool {
+        self.show_sentences || self.show_paragraphs
     }
 
-    WcCounts {
-        lines,
-        words,
-        chars,
+    fn wants_graphemes(&self) -> bool {
+        self.show_graphemes || self.show_width
+    }
+
+    fn wants_distinct(&self) -> bool {
+        self.show_distinct_lines || self.show_distinct_words
+    }
+
+    fn wants_eol(&self) -> bool {
+        self.show_eol || self.line_ending != LineEnding::Lf
     }
 }
 
-// 🔥 MAXIMUM SPEED memory-mapped file reading
-fn count_file_blazing_mmap(file_path: &str) -> Result<WcCounts, io::Error> {
-    #[cfg(unix)]
-    {
-        use std::fs::File;
-        use std::os::unix::io::AsRawFd;
+// 🧮 Optional counters that need their own streaming view of the bytes
+struct ExtraCounters {
+    prose: Option<ProseCounter>,
+    graphemes: Option<GraphemeCounter>,
+    tokens: Option<TokenCounter>,
+    markdown: Option<MarkdownCounter>,
+    distinct: Option<DistinctCounter>,
+    matches: Option<MatchCounter>,
+    utf8: Option<Utf8Validator>,
+    eol: Option<(EolCounter, LineEnding)>,
+    code: Option<CodeCounter>,
+}
 
-        let file = File::open(file_path)?;
-        let metadata = file.metadata()?;
-        let file_size = metadata.len() as usize;
+impl ExtraCounters {
+    fn new(config: &Config, file_path: Option<&str>) -> Self {
+        ExtraCounters {
+            prose: config.wants_prose().then(ProseCounter::new),
+            graphemes: config.wants_graphemes().then(GraphemeCounter::new),
+            tokens: config.tokenizer.clone().map(TokenCounter::new),
+            markdown: config.show_markdown.then(MarkdownCounter::new),
+            distinct: config.wants_distinct().then(|| {
+                DistinctCounter::new(
+                    config.show_distinct_lines,
+                    config.show_distinct_words,
+                    config.distinct_precision,
+                    config.distinct_exact,
+                )
+            }),
+            matches: (!config.matchers.is_empty()).then(|| MatchCounter::new(&config.matchers)),
+            utf8: (config.check_utf8 || config.strict_utf8).then(Utf8Validator::new),
+            eol: config
+                .wants_eol()
+                .then(|| (EolCounter::new(), config.line_ending)),
+            code: config.show_code.then(|| CodeCounter::new(file_path)),
+        }
+    }
 
-        if file_size == 0 {
-            return Ok(WcCounts::default());
+    #[inline(always)]
+    fn feed(&mut self, chunk: &[u8]) {
+        if let Some(prose) = &mut self.prose {
+            prose.feed(chunk);
+        }
+        if let Some(graphemes) = &mut self.graphemes {
+            graphemes.feed(chunk);
+        }
+        if let Some(tokens) = &mut self.tokens {
+            tokens.feed(chunk);
         }
+        if let Some(markdown) = &mut self.markdown {
+            markdown.feed(chunk);
+        }
+        if let Some(distinct) = &mut self.distinct {
+            distinct.feed(chunk);
+        }
+        if let Some(matches) = &mut self.matches {
+            matches.feed(chunk);
+        }
+        if let Some(utf8) = &mut self.utf8 {
+            utf8.feed(chunk);
+        }
+        if let Some((eol, _)) = &mut self.eol {
+            eol.feed(chunk);
+        }
+        if let Some(code) = &mut self.code {
+            code.feed(chunk);
+        }
+    }
 
-        let fd = file.as_raw_fd();
+    fn finish(self, counts: &mut WcCounts) {
+        if let Some(prose) = self.prose {
+            (counts.sentences, counts.paragraphs) = prose.finish();
+        }
+        if let Some(graphemes) = self.graphemes {
+            (counts.graphemes, counts.width) = graphemes.finish();
+        }
+        if let Some(tokens) = self.tokens {
+            counts.tokens = tokens.finish();
+        }
+        if let Some(markdown) = self.markdown {
+            (counts.prose_words, counts.code_words) = markdown.finish();
+        }
+        if let Some(distinct) = self.distinct {
+            (counts.distinct_lines, counts.distinct_words) = distinct.finish();
+        }
+        if let Some(matches) = self.matches {
+            counts.matches = matches.finish();
+        }
+        if let Some(utf8) = self.utf8 {
+            counts.utf8 = Some(utf8.finish());
+        }
+        if let Some((eol, line_ending)) = self.eol {
+            let eol = eol.finish();
+            // `--line-ending` redefines what `-l` counts
+            counts.lines = eol.lines(line_ending);
+            counts.eol = Some(eol);
+        }
+        if let Some(code) = self.code {
+            counts.code = Some(code.finish());
+        }
+    }
+}
 
-        unsafe {
-            let ptr = libc::mmap(
-                std::ptr::null_mut(),
-                file_size,
-                libc::PROT_READ,
-                libc::MAP_PRIVATE | libc::MAP_POPULATE,
-                fd,
-                0,
-            );
+// 📂 Counts one file with the backend `--io` picks for it
+fn count_file(file_path: &str, config: &Config) -> Result<WcCounts, io::Error> {
+    let started = Instant::now();
+    let file = File::open(file_path)?;
+
+    // 🧱 Sniff the first chunk before mapping or streaming the whole file
+    let (binary, unread) = sniff_binary(&file, config)?;
+    if binary.is_some() && config.binary == BinaryPolicy::Skip {
+        return Ok(WcCounts {
+            binary,
+            ..WcCounts::default()
+        });
+    }
 
-            if ptr == libc::MAP_FAILED {
-                return count_file_blazing_read(file_path);
-            }
+    let size = file.metadata()?.len();
+    let (counts, io) = match config.io.resolve(size, config.files.len()) {
+        // A pipe or device cannot rewind: count the sniffed prefix, then the rest
+        _ if unread.is_some() => {
+            let prefix = io::Cursor::new(unread.unwrap_or_default());
+            let mut reader = BufferedReader::new(prefix.chain(file));
+            (
+                count_chunks(&mut reader, config, Some(file_path))?,
+                IoStrategy::Read,
+            )
+        }
+        IoStrategy::Mmap => count_file_blazing_mmap(file, file_path, config)?,
+        #[cfg(unix)]
+        strategy if config.no_cache_pollution => {
+            use std::os::unix::io::AsRawFd;
+
+            let fd = file.as_raw_fd();
+            let (reader, io) = chunk_reader(file, strategy);
+            let mut reader = reader::DropBehind::new(reader, fd);
+            (count_chunks(&mut reader, config, Some(file_path))?, io)
+        }
+        strategy => {
+            let (mut reader, io) = chunk_reader(file, strategy);
+            (count_chunks(reader.as_mut(), config, Some(file_path))?, io)
+        }
+    };
 
-            // Prefetch for sequential access
-            libc::madvise(ptr, file_size, libc::MADV_SEQUENTIAL);
-            libc::madvise(ptr, file_size, libc::MADV_WILLNEED);
+    let timing = config.show_timings.then(|| Timing {
+        elapsed: started.elapsed(),
+        io: Some(io),
+    });
+    Ok(WcCounts {
+        binary,
+        timing,
+        ..counts
+    })
+}
 
-            let data = std::slice::from_raw_parts(ptr as *const u8, file_size);
-            let counts = count_bytes_blazing_speed(data);
+// 📖 The streaming backend for `strategy`; plain reads where it is unavailable.
+// Also returns the backend actually used, for `--timings`.
+fn chunk_reader(file: File, strategy: IoStrategy) -> (Box<dyn ChunkReader>, IoStrategy) {
+    match strategy {
+        #[cfg(unix)]
+        IoStrategy::Direct => (Box::new(reader::DirectReader::new(file)), strategy),
+        #[cfg(target_os = "linux")]
+        IoStrategy::Uring => match uring::UringReader::new(file) {
+            Ok(reader) => (Box::new(reader), strategy),
+            Err(file) => (Box::new(BufferedReader::new(file)), IoStrategy::Read),
+        },
+        _ => (Box::new(BufferedReader::new(file)), IoStrategy::Read),
+    }
+}
+
+// 🔥 MAXIMUM SPEED memory-mapped file reading, one window at a time. Files
+// that cannot be mapped are read instead, which the returned strategy says.
+fn count_file_blazing_mmap(
+    file: File,
+    file_path: &str,
+    config: &Config,
+) -> Result<(WcCounts, IoStrategy), io::Error> {
+    #[cfg(unix)]
+    {
+        let file_size = file.metadata()?.len();
 
-            libc::munmap(ptr, file_size);
-            Ok(counts)
+        // 🍃 Small windows keep little of a drop-behind scan in the page cache
+        let window = if config.no_cache_pollution {
+            reader::DROP_WINDOW
+        } else {
+            reader::MAP_WINDOW
+        };
+        let mut windows =
+            reader::MappedWindows::new(&file, file_size, window, config.no_cache_pollution);
+        let counts = count_chunks(&mut windows, config, Some(file_path));
+        if windows.unmappable() {
+            drop(windows);
+            let counts = count_chunks(&mut BufferedReader::new(file), config, Some(file_path))?;
+            return Ok((counts, IoStrategy::Read));
+        }
+        let truncated = windows.truncated();
+        drop(windows);
+
+        let now = file.metadata()?.len();
+        if truncated || now < file_size {
+            return Err(io::Error::other(format!(
+                "file shrank while being read ({file_size} -> {now} bytes)"
+            )));
         }
+        Ok((counts?, IoStrategy::Mmap))
     }
 
     #[cfg(not(unix))]
-    count_file_blazing_read(file_path)
+    {
+        let counts = count_chunks(&mut BufferedReader::new(file), config, Some(file_path))?;
+        Ok((counts, IoStrategy::Read))
+    }
 }
 
-// 🚀 Optimized read fallback with massive buffers
-fn count_file_blazing_read(file_path: &str) -> Result<WcCounts, io::Error> {
-    let mut file = File::open(file_path)?;
+// Reads at most `binary::SNIFF_SIZE` bytes and rewinds a regular file for the
+// counting pass. Anything else cannot seek, so the bytes read are returned to
+// be counted ahead of the rest of the stream.
+type Sniffed = (Option<&'static str>, Option<Vec<u8>>);
 
-    // Use 2MB buffer for maximum I/O efficiency
-    const BUFFER_SIZE: usize = 2 * 1024 * 1024;
-    let mut buffer = vec![0u8; BUFFER_SIZE];
-    let mut total_counts = WcCounts::default();
-    let mut last_was_word_char = false;
-    let mut first_chunk = true;
+fn sniff_binary(file: &File, config: &Config) -> Result<Sniffed, io::Error> {
+    if config.binary == BinaryPolicy::Count {
+        return Ok((None, None));
+    }
+    let mut prefix = Vec::with_capacity(binary::SNIFF_SIZE);
+    file.take(binary::SNIFF_SIZE as u64)
+        .read_to_end(&mut prefix)?;
+    let binary = binary::detect(&prefix, config.encoding);
+    if file.metadata()?.is_file() {
+        (&*file).seek(SeekFrom::Start(0))?;
+        Ok((binary, None))
+    } else {
+        Ok((binary, Some(prefix)))
+    }
+}
 
-    loop {
-        let bytes_read = file.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+// Transcodes non-UTF-8 input block by block and counts the UTF-8 result
+struct DecodingCounter {
+    decoder: Decoder,
+    decoded: Vec<u8>,
+    counter: Counter,
+}
+
+impl DecodingCounter {
+    // Keeps the transcoded block cache-sized whatever the input chunk size
+    const BLOCK_SIZE: usize = 64 * 1024;
+
+    fn new(encoding: Encoding) -> Self {
+        DecodingCounter {
+            decoder: Decoder::new(encoding),
+            decoded: Vec::with_capacity(Self::BLOCK_SIZE * 2),
+            counter: Counter::new(),
         }
+    }
 
-        let chunk = unsafe { buffer.get_unchecked(..bytes_read) };
-        let mut chunk_counts = count_bytes_blazing_speed(chunk);
+    fn feed(&mut self, chunk: &[u8], extras: &mut ExtraCounters) {
+        for block in chunk.chunks(Self::BLOCK_SIZE) {
+            self.decoded.clear();
+            self.decoder.decode(block, &mut self.decoded);
+            self.counter.feed(&self.decoded);
+            extras.feed(&self.decoded);
+        }
+    }
 
-        // Handle word boundaries across buffer boundaries
-        if !first_chunk && !chunk.is_empty() {
-            let first_byte = unsafe { *chunk.get_unchecked(0) };
-            let first_is_word_char = first_byte > b' ';
+    // `chars` stays the size of the raw input, like `wc -c`
+    fn finish(mut self, extras: &mut ExtraCounters, raw_bytes: u64) -> WcCounts {
+        self.decoded.clear();
+        self.decoder.finish(&mut self.decoded);
+        self.counter.feed(&self.decoded);
+        extras.feed(&self.decoded);
 
-            // If the previous chunk ended with a word character and this chunk
-            // starts with a word character, we counted one word too many
-            if last_was_word_char && first_is_word_char {
-                chunk_counts.words = chunk_counts.words.saturating_sub(1);
+        let mut counts = WcCounts::from(self.counter.finish());
+        counts.chars = raw_bytes;
+        counts
+    }
+}
+
+// Counts a fully in-memory input (mapped file or stdin)
+fn count_buffer(data: &[u8], config: &Config, file_path: Option<&str>) -> WcCounts {
+    let mut extras = ExtraCounters::new(config, file_path);
+    let (encoding, bom_len) = config.encoding.resolve(data);
+
+    let mut counts = if encoding == Encoding::Utf8 {
+        extras.feed(data);
+        WcCounts::from(count_bytes_blazing_speed(data))
+    } else {
+        let mut decoding = DecodingCounter::new(encoding);
+        decoding.feed(&data[bom_len..], &mut extras);
+        decoding.finish(&mut extras, data.len() as u64)
+    };
+
+    extras.finish(&mut counts);
+    counts
+}
+
+// 🚀 Counts a file streamed through any of the `--io` backends
+fn count_chunks(
+    reader: &mut dyn ChunkReader,
+    config: &Config,
+    file_path: Option<&str>,
+) -> Result<WcCounts, io::Error> {
+    let mut counter = Counter::new();
+    let mut decoding: Option<DecodingCounter> = None;
+    let mut extras = ExtraCounters::new(config, file_path);
+    let mut raw_bytes = 0;
+
+    while let Some(mut chunk) = reader.next_chunk()? {
+        let bytes_read = chunk.len();
+
+        // Every reader's first chunk holds any BOM whole: a regular file's
+        // first read is large, and pipe readers top up short reads
+        if raw_bytes == 0 {
+            let (encoding, bom_len) = config.encoding.resolve(chunk);
+            if encoding != Encoding::Utf8 {
+                decoding = Some(DecodingCounter::new(encoding));
+                chunk = &chunk[bom_len..];
             }
         }
-
-        // Remember the last character state for the next iteration
-        if !chunk.is_empty() {
-            let last_byte = unsafe { *chunk.get_unchecked(chunk.len() - 1) };
-            last_was_word_char = last_byte > b' ';
+        raw_bytes += bytes_read as u64;
+        if let Some(progress) = &config.progress {
+            progress.add_bytes(bytes_read as u64);
         }
 
-        total_counts.add(&chunk_counts);
-        first_chunk = false;
+        match &mut decoding {
+            Some(decoding) => decoding.feed(chunk, &mut extras),
+            None => {
+                counter.feed(chunk);
+                extras.feed(chunk);
+            }
+        }
     }
 
+    let mut total_counts = match decoding {
+        Some(decoding) => decoding.finish(&mut extras, raw_bytes),
+        None => WcCounts::from(counter.finish()),
+    };
+    extras.finish(&mut total_counts);
     Ok(total_counts)
 }
 
 // ⚡ PARALLEL processing optimized for maximum throughput
-fn count_files_parallel_blazing(file_paths: &[String]) -> Vec<Result<WcCounts, io::Error>> {
+fn count_files_parallel_blazing(
+    file_paths: &[String],
+    config: &Config,
+) -> Vec<Result<WcCounts, io::Error>> {
     use std::sync::mpsc;
 
     let num_threads = std::thread::available_parallelism()
@@ -300,15 +740,20 @@ fn count_files_parallel_blazing(file_paths: &[String]) -> Vec<Result<WcCounts, i
         .min(file_paths.len())
         .max(1);
 
+    let config = Arc::new(config.clone());
     let (tx, rx) = mpsc::channel();
     let mut handles = Vec::with_capacity(num_threads);
 
     for (index, file_path) in file_paths.iter().enumerate() {
         let tx = tx.clone();
         let file_path = file_path.clone();
+        let config = Arc::clone(&config);
 
         let handle = thread::spawn(move || {
-            let result = count_file_blazing_mmap(&file_path);
+            let result = count_file(&file_path, &config);
+            if let Some(progress) = &config.progress {
+                progress.file_done();
+            }
             tx.send((index, result)).unwrap();
         });
 
@@ -332,18 +777,42 @@ fn count_files_parallel_blazing(file_paths: &[String]) -> Vec<Result<WcCounts, i
     results
 }
 
+// 🚰 `--tee`: stdin flows on to stdout untouched while it is counted. Returns
+// the counts of what was forwarded, and whether stdout closed early.
+fn count_stdin_tee(config: &Config) -> Result<(WcCounts, bool), io::Error> {
+    let mut tee = reader::TeeReader::new(io::stdin().lock(), io::stdout().lock());
+    let counts = count_chunks(&mut tee, config, None)?;
+    Ok((counts, tee.output_closed()))
+}
+
+// The `--tee` report goes to stderr, or to the `--report-to` file
+fn write_report(report: &str, config: &Config) -> Result<(), io::Error> {
+    match &config.report_to {
+        Some(path) => std::fs::write(path, format!("{report}\n")),
+        None => {
+            eprintln!("{report}");
+            Ok(())
+        }
+    }
+}
+
 // 🚀 Blazing stdin processing
-fn count_stdin_blazing() -> Result<WcCounts, io::Error> {
+fn count_stdin_blazing(config: &Config) -> Result<WcCounts, io::Error> {
+    // Streamed instead, so `--progress` has something to show before EOF
+    if config.progress.is_some() {
+        return count_chunks(&mut BufferedReader::new(io::stdin().lock()), config, None);
+    }
+
     let mut stdin = io::stdin();
     let mut buffer = Vec::with_capacity(2 * 1024 * 1024);
 
     stdin.read_to_end(&mut buffer)?;
-    Ok(count_bytes_blazing_speed(&buffer))
+    Ok(count_buffer(&buffer, config, None))
 }
 
 #[inline(always)]
 fn format_output(counts: &WcCounts, config: &Config, filename: Option<&str>) -> String {
-    let mut parts = Vec::with_capacity(3);
+    let mut parts = Vec::with_capacity(8);
 
     if config.show_lines {
         parts.push(format!("{:8}", counts.lines));
@@ -354,6 +823,71 @@ fn format_output(counts: &WcCounts, config: &Config, filename: Option<&str>) ->
     if config.show_chars {
         parts.push(format!("{:8}", counts.chars));
     }
+    if config.show_sentences {
+        parts.push(format!("{:8}", counts.sentences));
+    }
+    if config.show_paragraphs {
+        parts.push(format!("{:8}", counts.paragraphs));
+    }
+    if config.show_graphemes {
+        parts.push(format!("{:8}", counts.graphemes));
+    }
+    if config.show_width {
+        parts.push(format!("{:8}", counts.width));
+    }
+    if config.tokenizer.is_some() {
+        parts.push(format!("{:8}", counts.tokens));
+    }
+    if config.show_markdown {
+        parts.push(format!("{:8}", counts.prose_words));
+        parts.push(format!("{:8}", counts.code_words));
+    }
+    if config.show_distinct_lines {
+        let distinct = counts.distinct_lines.as_ref().map_or(0, Distinct::count);
+        parts.push(format!("{distinct:8}"));
+    }
+    if config.show_distinct_words {
+        let distinct = counts.distinct_words.as_ref().map_or(0, Distinct::count);
+        parts.push(format!("{distinct:8}"));
+    }
+    for index in 0..config.matchers.len() {
+        let matched = counts.matches.get(index).copied().unwrap_or_default();
+        parts.push(format!("{:8}", matched.lines));
+        parts.push(format!("{:8}", matched.matches));
+    }
+    if config.check_utf8 {
+        let utf8 = counts.utf8.unwrap_or_default();
+        let first_invalid = match utf8.first_invalid {
+            Some((offset, line)) => format!("{offset}:{line}"),
+            None => "-".to_string(),
+        };
+        parts.push(format!("{:8}", utf8.invalid));
+        parts.push(format!("{first_invalid:>8}"));
+    }
+    if config.show_eol {
+        let eol = counts.eol.unwrap_or_default();
+        parts.push(format!("{:8}", eol.lf));
+        parts.push(format!("{:8}", eol.crlf));
+        parts.push(format!("{:8}", eol.cr));
+        parts.push(format!("{:8}", eol.unterminated));
+    }
+    if config.show_code {
+        let code = counts.code.clone().unwrap_or_default();
+        parts.push(format!("{:8}", code.code));
+        parts.push(format!("{:8}", code.comments));
+        parts.push(format!("{:8}", code.blanks));
+        let language = code.language.unwrap_or("");
+        parts.push(match filename {
+            Some(_) => format!("{language:<10}"),
+            None => language.to_string(),
+        });
+    }
+    if config.show_timings {
+        match &counts.timing {
+            Some(timing) => parts.extend(timing.columns(counts.chars)),
+            None => parts.extend(timings::blank_columns()),
+        }
+    }
 
     let mut output = parts.join(" ");
 
@@ -365,20 +899,98 @@ fn format_output(counts: &WcCounts, config: &Config, filename: Option<&str>) ->
     output
 }
 
+const USAGE: &str =
+    "Usage: wc [-lwcsp] [--graphemes] [--width] [--tokens=ENC [--vocab FILE]] [--distinct-lines] [--distinct-words] \
+[--distinct-precision=P] [--exact] [--code] [--markdown] \
+[--match PATTERN [-i] [-F]] [--encoding=ENC] \
+[--check-utf8] [--strict-utf8] [--binary=count|skip|warn] [--io=auto|mmap|read|direct|uring] [--no-cache-pollution] [--tee [--report-to FILE]] [--progress] [--timings] [--eol] [--line-ending=lf|crlf|cr|any] [file ...]
+   or: wc bench [--size=SIZE] [--trials=N] [--warmup=N] [--json]";
+
+// `--binary=skip|warn`: true if the file must be left out of the output
+fn handle_binary(
+    counts: &WcCounts,
+    config: &Config,
+    name: &str,
+    skipped: &mut Vec<String>,
+) -> bool {
+    match (counts.binary, config.binary) {
+        (Some(kind), BinaryPolicy::Skip) => {
+            skipped.push(format!("{name} ({kind})"));
+            true
+        }
+        (Some(kind), BinaryPolicy::Warn) => {
+            eprintln!("wc: {name}: binary file ({kind})");
+            false
+        }
+        _ => false,
+    }
+}
+
+// `--strict-utf8`: reports the first invalid sequence, true if there was one
+fn reject_invalid_utf8(counts: &WcCounts, config: &Config, name: &str) -> bool {
+    match counts.utf8.and_then(|utf8| utf8.first_invalid) {
+        Some((offset, line)) if config.strict_utf8 => {
+            eprintln!("wc: {name}: invalid UTF-8 at byte {offset} (line {line})");
+            true
+        }
+        _ => false,
+    }
+}
+
 fn main() {
+    // 🏁 A file actually named `bench` can still be counted as `./bench`
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("bench") {
+        if let Err(e) = bench::run(&args[2..]) {
+            eprintln!("Error: {e}");
+            eprintln!("Usage: {}", bench::USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let config = match Config::from_args() {
         Ok(config) => config,
         Err(e) => {
             eprintln!("Error: {e}");
-            eprintln!("Usage: wc [-l] [-w] [-c] [file ...]");
+            eprintln!("{USAGE}");
             std::process::exit(1);
         }
     };
 
-    if config.files.is_empty() {
-        match count_stdin_blazing() {
+    let mut invalid_utf8 = false;
+    let mut skipped_binary = Vec::new();
+    // Dropped (and its line cleared) before any result is printed
+    let reporter = config.progress.clone().map(Reporter::spawn);
+
+    if config.tee {
+        let result = count_stdin_tee(&config);
+        drop(reporter);
+        match result {
+            Ok((counts, output_closed)) => {
+                if let Err(e) = write_report(&format_output(&counts, &config, None), &config) {
+                    let path = config.report_to.as_deref().unwrap_or("-");
+                    eprintln!("wc: {path}: {e}");
+                    std::process::exit(1);
+                }
+                invalid_utf8 |= reject_invalid_utf8(&counts, &config, "-");
+                // Like a pipeline stage killed by SIGPIPE, but with the report written
+                if output_closed {
+                    std::process::exit(141);
+                }
+            }
+            Err(e) => {
+                eprintln!("Error reading stdin: {e}");
+                std::process::exit(1);
+            }
+        }
+    } else if config.files.is_empty() {
+        let result = count_stdin_blazing(&config);
+        drop(reporter);
+        match result {
             Ok(counts) => {
                 println!("{}", format_output(&counts, &config, None));
+                invalid_utf8 |= reject_invalid_utf8(&counts, &config, "-");
             }
             Err(e) => {
                 eprintln!("Error reading stdin: {e}");
@@ -387,9 +999,16 @@ fn main() {
         }
     } else if config.files.len() == 1 {
         let file_path = &config.files[0];
-        match count_file_blazing_mmap(file_path) {
+        let result = count_file(file_path, &config);
+        if let Some(progress) = &config.progress {
+            progress.file_done();
+        }
+        drop(reporter);
+        match result {
+            Ok(counts) if handle_binary(&counts, &config, file_path, &mut skipped_binary) => {}
             Ok(counts) => {
                 println!("{}", format_output(&counts, &config, Some(file_path)));
+                invalid_utf8 |= reject_invalid_utf8(&counts, &config, file_path);
             }
             Err(e) => {
                 eprintln!("wc: {file_path}: {e}");
@@ -397,15 +1016,34 @@ fn main() {
             }
         }
     } else {
-        let results = count_files_parallel_blazing(&config.files);
+        let started = Instant::now();
+        let results = count_files_parallel_blazing(&config.files, &config);
+        let wall = started.elapsed();
+        drop(reporter);
+        let mut busy = std::time::Duration::ZERO;
         let mut total_counts = WcCounts::default();
+        let mut language_counts: BTreeMap<&str, WcCounts> = BTreeMap::new();
 
         for (i, result) in results.into_iter().enumerate() {
             let file_path = &config.files[i];
             match result {
+                Ok(counts) if handle_binary(&counts, &config, file_path, &mut skipped_binary) => {}
                 Ok(counts) => {
                     println!("{}", format_output(&counts, &config, Some(file_path)));
+                    invalid_utf8 |= reject_invalid_utf8(&counts, &config, file_path);
                     total_counts.add(&counts);
+                    busy += counts
+                        .timing
+                        .map_or_else(Default::default, |timing| timing.elapsed);
+                    if let Some(language) = counts.code.as_ref().and_then(|code| code.language) {
+                        language_counts
+                            .entry(language)
+                            .and_modify(|language_total| language_total.add(&counts))
+                            .or_insert(WcCounts {
+                                timing: None,
+                                ..counts
+                            });
+                    }
                 }
                 Err(e) => {
                     eprintln!("wc: {file_path}: {e}");
@@ -414,8 +1052,34 @@ fn main() {
             }
         }
 
+        for language_total in language_counts.values() {
+            println!("{}", format_output(language_total, &config, Some("total")));
+        }
+        if config.show_timings {
+            total_counts.timing = Some(Timing {
+                elapsed: wall,
+                io: None,
+            });
+            let cores = thread::available_parallelism().map_or(1, |n| n.get());
+            // count_files_parallel_blazing spawns one thread per file
+            let summary = timings::utilisation(busy, wall, config.files.len(), cores);
+            eprintln!("wc: timings: {summary}");
+        }
         println!("{}", format_output(&total_counts, &config, Some("total")));
     }
+
+    if !skipped_binary.is_empty() {
+        let plural = if skipped_binary.len() == 1 { "" } else { "s" };
+        eprintln!(
+            "wc: skipped {} binary file{plural}: {}",
+            skipped_binary.len(),
+            skipped_binary.join(", ")
+        );
+    }
+
+    if invalid_utf8 {
+        std::process::exit(1);
+    }
 }
 
 // 🦀 Optimized libc bindings
@@ -428,7 +1092,30 @@ mod libc {
     pub const MADV_SEQUENTIAL: i32 = 2;
     pub const MADV_WILLNEED: i32 = 3;
 
+    // Signal structs differ between platforms, so they come from the libc crate
+    #[cfg(target_vendor = "apple")]
+    pub use ::libc::F_NOCACHE;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub use ::libc::O_DIRECT;
+    pub use ::libc::{fcntl, EINVAL, F_GETFL, F_SETFL};
+    pub use ::libc::{
+        sigaction, sigemptyset, siginfo_t, sysconf, _SC_PAGESIZE, SA_SIGINFO, SIGBUS, SIG_DFL,
+    };
+    // So do these mmap flags: MAP_ANONYMOUS is 0x20 on Linux but 0x1000 on
+    // macOS and the BSDs
+    pub use ::libc::{MAP_ANONYMOUS, MAP_FIXED};
+    // io_uring has no libc wrappers, only syscall numbers
+    #[cfg(target_os = "linux")]
+    pub use ::libc::{
+        close, iovec, syscall, SYS_io_uring_enter, SYS_io_uring_setup, MAP_SHARED, PROT_WRITE,
+    };
+
     extern "C" {
+        // 64-bit file offsets on 32-bit Linux too
+        #[cfg_attr(
+            all(target_os = "linux", target_pointer_width = "32"),
+            link_name = "mmap64"
+        )]
         pub fn mmap(
             addr: *mut std::ffi::c_void,
             len: usize,
@@ -442,6 +1129,22 @@ mod libc {
 
         pub fn madvise(addr: *mut std::ffi::c_void, len: usize, advice: i32) -> i32;
     }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const POSIX_FADV_SEQUENTIAL: i32 = 2;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const POSIX_FADV_WILLNEED: i32 = 3;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub const POSIX_FADV_DONTNEED: i32 = 4;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    extern "C" {
+        #[cfg_attr(
+            all(target_os = "linux", target_pointer_width = "32"),
+            link_name = "posix_fadvise64"
+        )]
+        pub fn posix_fadvise(fd: i32, offset: i64, len: i64, advice: i32) -> i32;
+    }
 }
 
 #[cfg(test)]
@@ -472,4 +1175,143 @@ mod tests {
         let counts = count_bytes_blazing_speed(large_text.as_bytes());
         assert_eq!(counts.words, 1000);
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_truncated_mid_count() {
+        use std::os::unix::io::AsRawFd;
+
+        let path = std::env::temp_dir().join(format!("wc-sigbus-{}.txt", std::process::id()));
+        std::fs::write(&path, "word ".repeat(1 << 20)).unwrap();
+        let file = File::open(&path).unwrap();
+        let size = file.metadata().unwrap().len() as usize;
+
+        unsafe {
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            );
+            assert_ne!(ptr, libc::MAP_FAILED);
+            let guard = sigbus::Guard::new(ptr as *const u8, size).unwrap();
+            let data = std::slice::from_raw_parts(ptr as *const u8, size);
+
+            // Count the first page, then cut the file down to it and count everything
+            let head = count_bytes_blazing_speed(&data[..4096]);
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_len(4096)
+                .unwrap();
+            let counts = count_bytes_blazing_speed(data);
+
+            // The vanished tail reads back as zeros (whitespace) instead of raising SIGBUS
+            assert!(guard.truncated());
+            assert_eq!(counts.words, head.words);
+            drop(guard);
+            libc::munmap(ptr, size);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_windows_carry_word_and_line_state() {
+        let path = std::env::temp_dir().join(format!("wc-window-{}.txt", std::process::id()));
+        // 7-byte words straddle every 64KB window boundary, and so does "é"
+        let text = "wörd é\n".repeat(40_000);
+        std::fs::write(&path, &text).unwrap();
+        let file = File::open(&path).unwrap();
+        let config = Config {
+            show_graphemes: true,
+            ..Config::default()
+        };
+
+        let size = text.len() as u64;
+        let mut windows = reader::MappedWindows::new(&file, size, 64 * 1024, false);
+        let counts = count_chunks(&mut windows, &config, Some("window.txt")).unwrap();
+        assert!(!windows.truncated());
+        let whole = count_buffer(text.as_bytes(), &config, None);
+        assert_eq!(
+            (counts.lines, counts.words, counts.chars, counts.graphemes),
+            (whole.lines, whole.words, whole.chars, whole.graphemes)
+        );
+        assert_eq!((counts.lines, counts.words), (40_000, 80_000));
+        drop(windows);
+
+        // Cut short after the first window: the rest reads as zeros and is flagged
+        let mut windows = reader::MappedWindows::new(&file, size, 64 * 1024, false);
+        windows.next_chunk().unwrap();
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(64 * 1024)
+            .unwrap();
+        while let Some(chunk) = windows.next_chunk().unwrap() {
+            assert!(chunk.iter().all(|&b| b == 0));
+        }
+        assert!(windows.truncated());
+        drop(windows);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chunk_paths_agree_on_random_input() {
+        // Hands out a buffer in chunks of the planned sizes
+        struct Planned<'a>(&'a [u8], Vec<usize>);
+        impl ChunkReader for Planned<'_> {
+            fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+                let len = self.1.pop().unwrap_or(self.0.len()).min(self.0.len());
+                let (chunk, rest) = self.0.split_at(len);
+                self.0 = rest;
+                Ok((!chunk.is_empty() || !rest.is_empty()).then_some(chunk))
+            }
+        }
+
+        let mut state = 0x853c_49e6_748f_ea9bu64;
+        let mut random = |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+        let path = std::env::temp_dir().join(format!("wc-random-{}.txt", std::process::id()));
+        let config = Config {
+            show_graphemes: true,
+            ..Config::default()
+        };
+
+        for _ in 0..20 {
+            let data: Vec<u8> = (0..random(300_000))
+                .map(|_| b"ab \n\t\x80\xc3\xa9"[random(8)])
+                .collect();
+            let whole = count_buffer(&data, &config, None);
+            let expected = (whole.lines, whole.words, whole.chars, whole.graphemes);
+
+            let plan = (0..64).map(|_| random(5000)).collect();
+            let counts = count_chunks(&mut Planned(&data, plan), &config, None).unwrap();
+            assert_eq!(
+                (counts.lines, counts.words, counts.chars, counts.graphemes),
+                expected
+            );
+
+            // 64KB windows, so words and characters straddle several of them
+            std::fs::write(&path, &data).unwrap();
+            let file = File::open(&path).unwrap();
+            let mut windows =
+                reader::MappedWindows::new(&file, data.len() as u64, 64 * 1024, random(2) == 0);
+            let counts = count_chunks(&mut windows, &config, None).unwrap();
+            assert_eq!(
+                (counts.lines, counts.words, counts.chars, counts.graphemes),
+                expected
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
 }
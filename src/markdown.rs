@@ -0,0 +1,410 @@
+// 🖋️ Markdown-aware word counting
+//
+// A lightweight CommonMark-ish line scanner: front matter, link targets,
+// images, HTML tags/comments and bare URLs are dropped, fenced/indented code
+// blocks and inline code spans are counted as code words, and everything else
+// is prose. A prose word must contain at least one alphanumeric character, so
+// list bullets, heading markers, table pipes and rules never count.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Block {
+    // Nothing seen yet: front matter is only recognised on the very first line
+    Start,
+    FrontMatter { closer: &'static str },
+    Prose,
+    Fence { marker: u8, len: usize },
+    HtmlComment,
+}
+
+pub struct MarkdownCounter {
+    block: Block,
+    partial: Vec<u8>,
+    in_paragraph: bool,
+    in_list: bool,
+    prose_words: u64,
+    code_words: u64,
+}
+
+impl Default for MarkdownCounter {
+    fn default() -> Self {
+        MarkdownCounter {
+            block: Block::Start,
+            partial: Vec::new(),
+            in_paragraph: false,
+            in_list: false,
+            prose_words: 0,
+            code_words: 0,
+        }
+    }
+}
+
+#[inline(always)]
+fn count_code_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+fn is_url(token: &str) -> bool {
+    token.contains("://") || token.starts_with("www.") || token.starts_with("mailto:")
+}
+
+fn count_prose_words(text: &str) -> usize {
+    text.split_whitespace()
+        .filter(|token| token.chars().any(char::is_alphanumeric) && !is_url(token))
+        .count()
+}
+
+// Up to three spaces of indentation are insignificant in CommonMark
+fn strip_indent(line: &str) -> Option<&str> {
+    let spaces = line.bytes().take_while(|&b| b == b' ').count();
+    (spaces <= 3).then(|| &line[spaces..])
+}
+
+fn fence_open(line: &str) -> Option<(u8, usize)> {
+    let trimmed = strip_indent(line)?;
+    let marker = *trimmed.as_bytes().first()?;
+    if marker != b'`' && marker != b'~' {
+        return None;
+    }
+    let len = trimmed.bytes().take_while(|&b| b == marker).count();
+    // Backtick fences may not have backticks in the info string
+    (len >= 3 && !(marker == b'`' && trimmed[len..].contains('`'))).then_some((marker, len))
+}
+
+fn is_indented_code(line: &str) -> bool {
+    line.starts_with('\t') || line.starts_with("    ")
+}
+
+// `[id]: https://example.com "title"`
+fn is_link_definition(line: &str) -> bool {
+    strip_indent(line)
+        .and_then(|rest| rest.strip_prefix('['))
+        .and_then(|rest| rest.find("]:").map(|end| end > 0))
+        .unwrap_or(false)
+}
+
+// Bullet (`-`, `*`, `+`) or ordered (`1.`, `1)`) list item marker
+fn list_marker_len(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    let marker = match rest.as_bytes().first()? {
+        b'-' | b'*' | b'+' => 1,
+        b'0'..=b'9' => {
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            match rest.as_bytes().get(digits) {
+                Some(b'.') | Some(b')') if digits <= 9 => digits + 1,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    match rest.as_bytes().get(marker) {
+        None | Some(b' ') | Some(b'\t') => Some(indent + marker),
+        _ => None,
+    }
+}
+
+// Index just past the `)` matching the `(` at `start`
+fn skip_parens(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+impl MarkdownCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut rest = chunk;
+        while let Some(pos) = rest.iter().position(|&b| b == b'\n') {
+            if self.partial.is_empty() {
+                self.line(&rest[..pos]);
+            } else {
+                let mut line = std::mem::take(&mut self.partial);
+                line.extend_from_slice(&rest[..pos]);
+                self.line(&line);
+                line.clear();
+                self.partial = line;
+            }
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+    }
+
+    // Returns (prose words, code words)
+    pub fn finish(mut self) -> (u64, u64) {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.line(&line);
+        }
+        (self.prose_words, self.code_words)
+    }
+
+    fn line(&mut self, raw: &[u8]) {
+        let line = String::from_utf8_lossy(raw);
+        let line = line.trim_end_matches('\r');
+
+        match self.block {
+            Block::Start => {
+                self.block = Block::Prose;
+                match line.trim_end() {
+                    "---" => self.block = Block::FrontMatter { closer: "---" },
+                    "+++" => self.block = Block::FrontMatter { closer: "+++" },
+                    _ => self.prose_line(line),
+                }
+            }
+            Block::FrontMatter { closer } => {
+                let trimmed = line.trim_end();
+                if trimmed == closer || (closer == "---" && trimmed == "...") {
+                    self.block = Block::Prose;
+                }
+            }
+            Block::Fence { marker, len } => {
+                let closes = strip_indent(line).is_some_and(|trimmed| {
+                    let run = trimmed.bytes().take_while(|&b| b == marker).count();
+                    run >= len && trimmed[run..].trim().is_empty()
+                });
+                if closes {
+                    self.block = Block::Prose;
+                } else {
+                    self.code_words += count_code_words(line) as u64;
+                }
+            }
+            Block::HtmlComment => {
+                if let Some(end) = line.find("-->") {
+                    self.block = Block::Prose;
+                    self.inline(&line[end + 3..]);
+                }
+            }
+            Block::Prose => self.prose_line(line),
+        }
+    }
+
+    fn prose_line(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            self.in_paragraph = false;
+            return;
+        }
+
+        if let Some((marker, len)) = fence_open(line) {
+            self.block = Block::Fence { marker, len };
+            self.in_paragraph = false;
+            return;
+        }
+
+        // Indented code cannot interrupt a paragraph or continue a list item
+        if is_indented_code(line) && !self.in_paragraph && !self.in_list {
+            self.code_words += count_code_words(line) as u64;
+            return;
+        }
+
+        if is_link_definition(line) {
+            return;
+        }
+
+        let content = match list_marker_len(line) {
+            Some(marker) => {
+                self.in_list = true;
+                &line[marker..]
+            }
+            None => {
+                if !self.in_paragraph && !line.starts_with([' ', '\t']) {
+                    self.in_list = false;
+                }
+                line
+            }
+        };
+
+        self.in_paragraph = true;
+        self.inline(content);
+    }
+
+    // Splits a line into prose and code-span text, dropping markup
+    fn inline(&mut self, line: &str) {
+        let bytes = line.as_bytes();
+        let mut prose = String::with_capacity(line.len());
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if i + 1 < bytes.len() => {
+                    // The escaped character is literal text, as in CommonMark
+                    let len = line[i + 1..].chars().next().map_or(1, char::len_utf8);
+                    prose.push_str(&line[i + 1..i + 1 + len]);
+                    i += 1 + len;
+                }
+                b'`' => {
+                    let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                    let body = i + run;
+                    let closing = line[body..].match_indices(&line[i..body]).find(|&(at, _)| {
+                        // The closing run must be exactly as long as the opening one
+                        bytes.get(body + at + run) != Some(&b'`')
+                    });
+                    match closing {
+                        Some((at, _)) => {
+                            self.code_words += count_code_words(&line[body..body + at]) as u64;
+                            prose.push(' ');
+                            i = body + at + run;
+                        }
+                        None => {
+                            prose.push_str(&line[i..body]);
+                            i = body;
+                        }
+                    }
+                }
+                b'!' if bytes.get(i + 1) == Some(&b'[') => {
+                    // Images are dropped entirely, alt text included
+                    match line[i..].find(']') {
+                        Some(close) => {
+                            i += close + 1;
+                            if bytes.get(i) == Some(&b'(') {
+                                i = skip_parens(bytes, i);
+                            } else if bytes.get(i) == Some(&b'[') {
+                                i = line[i..].find(']').map_or(bytes.len(), |end| i + end + 1);
+                            }
+                            prose.push(' ');
+                        }
+                        None => {
+                            prose.push('!');
+                            i += 1;
+                        }
+                    }
+                }
+                b']' => {
+                    prose.push(' ');
+                    i += 1;
+                    if bytes.get(i) == Some(&b'(') {
+                        i = skip_parens(bytes, i);
+                    } else if bytes.get(i) == Some(&b'[') {
+                        i = line[i..].find(']').map_or(bytes.len(), |end| i + end + 1);
+                    }
+                }
+                b'<' if line[i..].starts_with("<!--") => match line[i + 4..].find("-->") {
+                    Some(end) => {
+                        prose.push(' ');
+                        i += 4 + end + 3;
+                    }
+                    None => {
+                        self.block = Block::HtmlComment;
+                        break;
+                    }
+                },
+                b'<' if bytes
+                    .get(i + 1)
+                    .is_some_and(|&b| b.is_ascii_alphabetic() || b == b'/' || b == b'!') =>
+                {
+                    // HTML tags and autolinks
+                    match line[i..].find('>') {
+                        Some(end) => {
+                            prose.push(' ');
+                            i += end + 1;
+                        }
+                        None => {
+                            prose.push('<');
+                            i += 1;
+                        }
+                    }
+                }
+                b'[' | b'|' => {
+                    prose.push(' ');
+                    i += 1;
+                }
+                _ => {
+                    let next = line[i..]
+                        .find(['\\', '`', '!', ']', '<', '[', '|'])
+                        .map_or(bytes.len(), |at| (i + at).max(i + 1));
+                    prose.push_str(&line[i..next]);
+                    i = next;
+                }
+            }
+        }
+
+        self.prose_words += count_prose_words(&prose) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(text: &str) -> (u64, u64) {
+        let mut counter = MarkdownCounter::new();
+        counter.feed(text.as_bytes());
+        counter.finish()
+    }
+
+    #[test]
+    fn test_markup_is_not_counted() {
+        assert_eq!(count("## Hello world\n"), (2, 0));
+        assert_eq!(count("- item one\n* item two\n1. third item\n"), (6, 0));
+        assert_eq!(
+            count("See [the docs](https://example.com/a_(b)) now.\n"),
+            (4, 0)
+        );
+        assert_eq!(
+            count("![logo](img.png) <b>bold</b> text <!-- hidden -->\n"),
+            (2, 0)
+        );
+        assert_eq!(count("| a | b |\n|---|---|\n| c | d |\n"), (4, 0));
+        assert_eq!(
+            count("Visit https://x.io or <https://y.io>.\n\n[x]: http://z\n"),
+            (2, 0)
+        );
+    }
+
+    #[test]
+    fn test_code_is_counted_separately() {
+        let text = "Run `cargo build --release` now.\n\n```rust\nfn main() {}\n```\n\n    indented code here\n\nDone.\n";
+        assert_eq!(count(text), (3, 9));
+    }
+
+    #[test]
+    fn test_front_matter_and_multiline_comments() {
+        let text =
+            "---\ntitle: Skip me\ntags: [a, b]\n---\nReal words.\n<!--\nhidden\nwords -->visible\n";
+        assert_eq!(count(text), (3, 0));
+        // A rule later in the document is not front matter
+        assert_eq!(count("Intro\n---\ntext\n"), (2, 0));
+    }
+
+    #[test]
+    fn test_list_continuation_is_prose() {
+        let text = "- item\n\n    continued paragraph\n";
+        assert_eq!(count(text), (3, 0));
+    }
+
+    #[test]
+    fn test_escaped_multibyte_character() {
+        assert_eq!(count("caf\\é au lait \\😀 \\*x\\*\n"), (4, 0));
+        // An escape does not split the word it sits in
+        assert_eq!(count("snake\\_case here\n"), count("snake_case here\n"));
+        assert_eq!(count("snake\\_case here\n"), (2, 0));
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let text = "---\na: b\n---\n# Title\n\n```\nlet x = 1;\n```\nSome `code` and [link](http://e.com).\ncaf\\é \\ж\n";
+        let expected = count(text);
+        for split in 0..=text.len() {
+            let mut counter = MarkdownCounter::new();
+            counter.feed(&text.as_bytes()[..split]);
+            counter.feed(&text.as_bytes()[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+    }
+}
@@ -0,0 +1,327 @@
+// 🔍 Pattern-match counting (`--match`) in the same pass as the counters
+//
+// Literal patterns take a SWAR memchr fast path over whole runs of complete
+// lines; regular expressions (behind the `regex` feature) run line by line.
+// Matches never span lines and are counted non-overlapping, like `grep -o`.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MatchCounts {
+    pub lines: u64,
+    pub matches: u64,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Fixed {
+        needle: Vec<u8>,
+        ignore_case: bool,
+    },
+    #[cfg(feature = "regex")]
+    Regex(regex::bytes::Regex),
+}
+
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    kind: Kind,
+}
+
+const REGEX_META: &[u8] = b"\\.+*?()|[]{}^$";
+
+impl Matcher {
+    // Patterns without metacharacters always use the literal fast path
+    pub fn new(pattern: &str, fixed: bool, ignore_case: bool) -> Result<Self, String> {
+        if pattern.is_empty() {
+            return Err("--match: empty pattern".to_string());
+        }
+        if pattern.contains('\n') {
+            return Err(format!("--match: pattern {pattern:?} spans lines"));
+        }
+
+        let literal = fixed || !pattern.bytes().any(|b| REGEX_META.contains(&b));
+        // Unicode case folding needs the regex engine; ASCII folding does not
+        if literal && (!ignore_case || pattern.is_ascii() || !cfg!(feature = "regex")) {
+            let needle = if ignore_case {
+                pattern.to_ascii_lowercase().into_bytes()
+            } else {
+                pattern.as_bytes().to_vec()
+            };
+            return Ok(Matcher {
+                kind: Kind::Fixed {
+                    needle,
+                    ignore_case,
+                },
+            });
+        }
+
+        #[cfg(feature = "regex")]
+        {
+            let source = if literal {
+                regex::escape(pattern)
+            } else {
+                pattern.to_string()
+            };
+            regex::bytes::RegexBuilder::new(&source)
+                .case_insensitive(ignore_case)
+                .build()
+                .map(|regex| Matcher {
+                    kind: Kind::Regex(regex),
+                })
+                .map_err(|e| format!("--match: {e}"))
+        }
+
+        #[cfg(not(feature = "regex"))]
+        Err(format!(
+            "--match: {pattern:?} looks like a regular expression, but this build has no \
+             regex support (rebuild with `--features regex`, or pass -F for a fixed string)"
+        ))
+    }
+
+    // `region` must hold complete lines only (the last one may lack its newline)
+    fn count(&self, region: &[u8], counts: &mut MatchCounts) {
+        match &self.kind {
+            Kind::Fixed {
+                needle,
+                ignore_case,
+            } => {
+                let mut pos = 0;
+                let mut line_end = None;
+                while let Some(found) = find(&region[pos..], needle, *ignore_case) {
+                    let at = pos + found;
+                    counts.matches += 1;
+                    if line_end.is_none_or(|end| at > end) {
+                        counts.lines += 1;
+                        line_end =
+                            Some(memchr(b'\n', &region[at..]).map_or(region.len(), |n| at + n));
+                    }
+                    pos = at + needle.len();
+                }
+            }
+            #[cfg(feature = "regex")]
+            Kind::Regex(regex) => {
+                let region = region.strip_suffix(b"\n").unwrap_or(region);
+                for line in region.split(|&b| b == b'\n') {
+                    let matches = regex.find_iter(line).count() as u64;
+                    counts.matches += matches;
+                    counts.lines += (matches > 0) as u64;
+                }
+            }
+        }
+    }
+}
+
+const LO: u64 = 0x0101_0101_0101_0101;
+const HI: u64 = 0x8080_8080_8080_8080;
+
+// Nonzero iff some byte of `word` is zero (exact for the lowest such byte)
+#[inline(always)]
+fn has_zero(word: u64) -> u64 {
+    word.wrapping_sub(LO) & !word & HI
+}
+
+// 🚀 Word-at-a-time byte search
+#[inline(always)]
+pub fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+    let repeated = LO * needle as u64;
+    let mut chunks = haystack.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        let zero = has_zero(word ^ repeated);
+        if zero != 0 {
+            return Some(offset + (zero.trailing_zeros() / 8) as usize);
+        }
+        offset += 8;
+    }
+    chunks
+        .remainder()
+        .iter()
+        .position(|&b| b == needle)
+        .map(|i| offset + i)
+}
+
+// Either of two bytes, for ASCII case-insensitive first-byte scans
+#[inline(always)]
+fn memchr2(a: u8, b: u8, haystack: &[u8]) -> Option<usize> {
+    let (repeated_a, repeated_b) = (LO * a as u64, LO * b as u64);
+    let mut chunks = haystack.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let word = u64::from_le_bytes(chunk.try_into().unwrap());
+        let zero = has_zero(word ^ repeated_a) | has_zero(word ^ repeated_b);
+        if zero != 0 {
+            return Some(offset + (zero.trailing_zeros() / 8) as usize);
+        }
+        offset += 8;
+    }
+    chunks
+        .remainder()
+        .iter()
+        .position(|&byte| byte == a || byte == b)
+        .map(|i| offset + i)
+}
+
+// `needle` is already lowercased when `ignore_case` is set
+fn find(haystack: &[u8], needle: &[u8], ignore_case: bool) -> Option<usize> {
+    let first = needle[0];
+    let mut pos = 0;
+    while pos + needle.len() <= haystack.len() {
+        let candidate = &haystack[pos..=haystack.len() - needle.len()];
+        let found = if ignore_case {
+            memchr2(first, first.to_ascii_uppercase(), candidate)
+        } else {
+            memchr(first, candidate)
+        }?;
+        let at = pos + found;
+        let window = &haystack[at..at + needle.len()];
+        let equal = if ignore_case {
+            window.eq_ignore_ascii_case(needle)
+        } else {
+            window == needle
+        };
+        if equal {
+            return Some(at);
+        }
+        pos = at + 1;
+    }
+    None
+}
+
+// Streaming counter: whole lines are matched in place, only the trailing
+// partial line is carried to the next chunk
+pub struct MatchCounter {
+    matchers: Vec<Matcher>,
+    counts: Vec<MatchCounts>,
+    partial: Vec<u8>,
+}
+
+impl MatchCounter {
+    pub fn new(matchers: &[Matcher]) -> Self {
+        MatchCounter {
+            matchers: matchers.to_vec(),
+            counts: vec![MatchCounts::default(); matchers.len()],
+            partial: Vec::new(),
+        }
+    }
+
+    fn count_region(&mut self, region: &[u8]) {
+        for (matcher, counts) in self.matchers.iter().zip(&mut self.counts) {
+            matcher.count(region, counts);
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let Some(last_newline) = chunk.iter().rposition(|&b| b == b'\n') else {
+            self.partial.extend_from_slice(chunk);
+            return;
+        };
+        let (lines, rest) = chunk.split_at(last_newline + 1);
+
+        if self.partial.is_empty() {
+            self.count_region(lines);
+        } else {
+            // Complete the carried line first, then the rest in place
+            let first_newline = memchr(b'\n', lines).unwrap();
+            let mut line = std::mem::take(&mut self.partial);
+            line.extend_from_slice(&lines[..=first_newline]);
+            self.count_region(&line);
+            self.count_region(&lines[first_newline + 1..]);
+            line.clear();
+            self.partial = line;
+        }
+        self.partial.extend_from_slice(rest);
+    }
+
+    pub fn finish(mut self) -> Vec<MatchCounts> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.count_region(&line);
+        }
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(pattern: &str, ignore_case: bool, text: &[u8], chunk_size: usize) -> MatchCounts {
+        let matcher = Matcher::new(pattern, false, ignore_case).unwrap();
+        let mut counter = MatchCounter::new(&[matcher]);
+        for chunk in text.chunks(chunk_size) {
+            counter.feed(chunk);
+        }
+        counter.finish()[0]
+    }
+
+    #[test]
+    fn test_memchr_matches_naive_search() {
+        let haystack: Vec<u8> = (0..200u8).map(|i| i.wrapping_mul(37)).collect();
+        for needle in 0..=255u8 {
+            for start in 0..9 {
+                let expected = haystack[start..].iter().position(|&b| b == needle);
+                assert_eq!(memchr(needle, &haystack[start..]), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_counts_across_chunks() {
+        let text = b"ERROR one ERROR\nok\nerror two\nERROR";
+        for chunk_size in 1..=text.len() {
+            let exact = count("ERROR", false, text, chunk_size);
+            assert_eq!(
+                exact,
+                MatchCounts {
+                    lines: 2,
+                    matches: 3
+                }
+            );
+            let folded = count("error", true, text, chunk_size);
+            assert_eq!(
+                folded,
+                MatchCounts {
+                    lines: 3,
+                    matches: 4
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_overlapping_matches() {
+        assert_eq!(
+            count("aa", false, b"aaaa\naaa\n", 4),
+            MatchCounts {
+                lines: 2,
+                matches: 3
+            }
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_regex_counts() {
+        let text = b"GET /a 200\nPOST /b 500\nGET /c 404\n";
+        assert_eq!(
+            count(r"\b[45]\d\d\b", false, text, 7),
+            MatchCounts {
+                lines: 2,
+                matches: 2
+            }
+        );
+        assert_eq!(
+            count("^get", true, text, 3),
+            MatchCounts {
+                lines: 2,
+                matches: 2
+            }
+        );
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn test_regex_requires_feature() {
+        assert!(Matcher::new("a.*b", false, false).is_err());
+        assert!(Matcher::new("a.*b", true, false).is_ok());
+    }
+}
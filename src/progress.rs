@@ -0,0 +1,211 @@
+// 📈 `--progress`: bytes, files, throughput and ETA on stderr
+//
+// Worker threads bump atomic counters; a reporter thread redraws one line in
+// place when stderr is a terminal, or prints a plain line every few seconds
+// when it is redirected to a log.
+
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub struct Progress {
+    files_total: usize,
+    // Sizes of the files as they were when counting started; 0 for stdin
+    bytes_total: u64,
+    files_done: AtomicUsize,
+    bytes_done: AtomicU64,
+    started: Instant,
+}
+
+impl Progress {
+    pub fn new(files: &[String]) -> Self {
+        let bytes_total = files
+            .iter()
+            .filter_map(|file| std::fs::metadata(file).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        Progress {
+            files_total: files.len(),
+            bytes_total,
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+            started: Instant::now(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn add_bytes(&self, bytes: u64) {
+        self.bytes_done.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn file_done(&self) {
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn line(&self) -> String {
+        let done = self.bytes_done.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let mut line = human_bytes(done as f64);
+        if self.bytes_total > 0 {
+            let percent = (done as f64 * 100.0 / self.bytes_total as f64).min(100.0);
+            line += &format!(
+                " / {} ({percent:.1}%)",
+                human_bytes(self.bytes_total as f64)
+            );
+        }
+        if self.files_total > 0 {
+            let files_done = self.files_done.load(Ordering::Relaxed);
+            line += &format!(", {files_done}/{} files", self.files_total);
+        }
+        line += &format!(", {}/s", human_bytes(rate));
+        if self.bytes_total > done && rate > 0.0 {
+            let eta = (self.bytes_total - done) as f64 / rate;
+            line += &format!(", ETA {}", human_duration(eta));
+        }
+        line
+    }
+}
+
+fn human_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{value:.0} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+fn human_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3599 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+// Draws the progress line until dropped, then clears it
+pub struct Reporter {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Reporter {
+    pub fn spawn(progress: Arc<Progress>) -> Self {
+        let terminal = io::stderr().is_terminal();
+        let interval = if terminal {
+            REDRAW_INTERVAL
+        } else {
+            LOG_INTERVAL
+        };
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let thread = thread::spawn(move || loop {
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) if terminal => {
+                    eprint!("\r\x1b[K{}", progress.line());
+                }
+                Err(RecvTimeoutError::Timeout) => eprintln!("wc: progress: {}", progress.line()),
+                _ => {
+                    if terminal {
+                        eprint!("\r\x1b[K");
+                    }
+                    break;
+                }
+            }
+        });
+
+        Reporter {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        // Hanging up wakes the reporter immediately
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_units() {
+        assert_eq!(human_bytes(512.0), "512 B");
+        assert_eq!(human_bytes(1536.0), "1.5 KiB");
+        assert_eq!(
+            human_bytes(2.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0),
+            "2.0 TiB"
+        );
+        assert_eq!(human_duration(42.0), "42s");
+        assert_eq!(human_duration(125.0), "2m05s");
+        assert_eq!(human_duration(9000.0), "2h30m");
+    }
+
+    #[test]
+    fn test_counters_from_threads() {
+        let progress = Arc::new(Progress {
+            files_total: 8,
+            bytes_total: 8 * 1000 * 4096,
+            files_done: AtomicUsize::new(0),
+            bytes_done: AtomicU64::new(0),
+            started: Instant::now() - Duration::from_secs(1),
+        });
+        let workers: Vec<_> = (0..8)
+            .map(|_| {
+                let progress = Arc::clone(&progress);
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        progress.add_bytes(2048);
+                    }
+                    progress.file_done();
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let line = progress.line();
+        assert!(
+            line.starts_with("15.6 MiB / 31.2 MiB (50.0%), 8/8 files, "),
+            "{line}"
+        );
+        assert!(line.contains("ETA "), "{line}");
+    }
+
+    #[test]
+    fn test_stdin_has_no_totals() {
+        let progress = Progress::new(&[]);
+        progress.add_bytes(100);
+        let line = progress.line();
+        assert!(line.starts_with("100 B, "), "{line}");
+        assert!(!line.contains("files") && !line.contains("ETA"), "{line}");
+    }
+}
@@ -0,0 +1,266 @@
+// 📝 Sentence and paragraph counting for prose
+//
+// Paragraphs are runs of non-blank lines separated by blank lines. Sentences
+// end at a run of terminators (`.`, `!`, `?`, `。`, ...) followed by whitespace,
+// optionally through closing quotes/brackets. A `.` that completes a known
+// abbreviation or a single-letter initial does not end the sentence, and any
+// open sentence is closed by a paragraph break or the end of input.
+
+// Compared case-insensitively against the word including its trailing dot
+const ABBREVIATIONS: &[&[u8]] = &[
+    b"mr.", b"mrs.", b"ms.", b"dr.", b"prof.", b"sr.", b"jr.", b"st.", b"mt.", b"vs.", b"e.g.",
+    b"i.e.", b"cf.", b"al.", b"approx.", b"no.", b"nos.", b"vol.", b"fig.", b"figs.", b"eq.",
+    b"inc.", b"ltd.", b"co.", b"corp.", b"dept.", b"gen.", b"col.", b"capt.", b"lt.", b"sgt.",
+    b"rev.", b"hon.", b"jan.", b"feb.", b"mar.", b"apr.", b"jun.", b"jul.", b"aug.", b"sep.",
+    b"sept.", b"oct.", b"nov.", b"dec.", b"p.", b"pp.", b"ch.", b"sec.", b"a.m.", b"p.m.",
+];
+
+// Longest abbreviation we ever need to look back over
+const MAX_WORD: usize = 8;
+
+#[inline(always)]
+fn is_terminator(c: char) -> bool {
+    matches!(
+        c,
+        '.' | '!'
+            | '?'
+            | '…'
+            | '‼'
+            | '⁇'
+            | '⁈'
+            | '⁉'
+            | '。'
+            | '．'
+            | '！'
+            | '？'
+            | '｡'
+            | '।'
+            | '॥'
+            | '؟'
+            | '۔'
+            | '։'
+            | '።'
+            | '჻'
+    )
+}
+
+// CJK text does not put whitespace after these, so they end the sentence outright
+#[inline(always)]
+fn is_fullwidth_terminator(c: char) -> bool {
+    matches!(c, '。' | '．' | '！' | '？' | '｡')
+}
+
+#[inline(always)]
+fn is_closing(c: char) -> bool {
+    matches!(
+        c,
+        '"' | '\'' | ')' | ']' | '}' | '”' | '’' | '»' | '›' | '」' | '』' | '）'
+    )
+}
+
+#[derive(Default)]
+pub struct ProseCounter {
+    sentences: u64,
+    paragraphs: u64,
+    in_paragraph: bool,
+    line_has_text: bool,
+    sentence_open: bool,
+    pending_end: bool,
+    pending_fullwidth: bool,
+    // Head of the current word, for abbreviation lookups
+    word: [u8; MAX_WORD],
+    word_len: usize,
+    word_overflow: bool,
+    // Partial UTF-8 sequence carried over from the previous chunk
+    utf8: [u8; 4],
+    utf8_len: usize,
+    utf8_need: usize,
+}
+
+impl ProseCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &byte in chunk {
+            if self.utf8_need > 0 {
+                if byte & 0xC0 == 0x80 {
+                    self.utf8[self.utf8_len] = byte;
+                    self.utf8_len += 1;
+                    if self.utf8_len == self.utf8_need {
+                        let c = std::str::from_utf8(&self.utf8[..self.utf8_len])
+                            .ok()
+                            .and_then(|s| s.chars().next())
+                            .unwrap_or(char::REPLACEMENT_CHARACTER);
+                        self.utf8_need = 0;
+                        self.char(c);
+                    }
+                    continue;
+                }
+                // Truncated sequence: count it as one opaque character
+                self.utf8_need = 0;
+                self.char(char::REPLACEMENT_CHARACTER);
+            }
+
+            match byte {
+                0x00..=0x7F => self.char(byte as char),
+                0xC2..=0xF4 => {
+                    self.utf8[0] = byte;
+                    self.utf8_len = 1;
+                    self.utf8_need = match byte {
+                        0xC2..=0xDF => 2,
+                        0xE0..=0xEF => 3,
+                        _ => 4,
+                    };
+                }
+                _ => self.char(char::REPLACEMENT_CHARACTER),
+            }
+        }
+    }
+
+    // Returns (sentences, paragraphs)
+    pub fn finish(mut self) -> (u64, u64) {
+        if self.utf8_need > 0 {
+            self.char(char::REPLACEMENT_CHARACTER);
+        }
+        self.close_sentence();
+        (self.sentences, self.paragraphs)
+    }
+
+    #[inline(always)]
+    fn close_sentence(&mut self) {
+        if self.sentence_open {
+            self.sentences += 1;
+        }
+        self.sentence_open = false;
+        self.pending_end = false;
+        self.pending_fullwidth = false;
+    }
+
+    #[inline(always)]
+    fn push_word(&mut self, c: char) {
+        if self.word_len < MAX_WORD && c.is_ascii() {
+            self.word[self.word_len] = c as u8;
+            self.word_len += 1;
+        } else {
+            self.word_overflow = true;
+        }
+    }
+
+    fn is_abbreviation(&self) -> bool {
+        if self.word_overflow {
+            return false;
+        }
+        let word = &self.word[..self.word_len];
+        // Leading quotes/brackets are not part of the abbreviation
+        let start = word
+            .iter()
+            .position(|b| b.is_ascii_alphanumeric())
+            .unwrap_or(word.len());
+        let word = &word[start..];
+
+        // Single-letter initials such as "J. R. R. Tolkien"
+        (word.len() == 2 && word[0].is_ascii_uppercase())
+            || ABBREVIATIONS
+                .iter()
+                .any(|abbreviation| abbreviation.eq_ignore_ascii_case(word))
+    }
+
+    #[inline(always)]
+    fn char(&mut self, c: char) {
+        if c.is_whitespace() {
+            if self.pending_end {
+                self.close_sentence();
+            }
+            self.word_len = 0;
+            self.word_overflow = false;
+
+            if c == '\n' {
+                if !self.line_has_text {
+                    // Blank line: paragraph (and any open sentence) ends here
+                    self.in_paragraph = false;
+                    self.close_sentence();
+                }
+                self.line_has_text = false;
+            }
+            return;
+        }
+
+        if !self.line_has_text {
+            self.line_has_text = true;
+            if !self.in_paragraph {
+                self.in_paragraph = true;
+                self.paragraphs += 1;
+            }
+        }
+
+        if is_terminator(c) {
+            self.push_word(c);
+            if self.sentence_open && !(c == '.' && !self.pending_end && self.is_abbreviation()) {
+                self.pending_end = true;
+                self.pending_fullwidth |= is_fullwidth_terminator(c);
+            }
+        } else if self.pending_end && is_closing(c) {
+            self.push_word(c);
+        } else {
+            if self.pending_fullwidth {
+                self.close_sentence();
+            }
+            // "3.14", "example.com": the terminator was inside a word
+            self.pending_end = false;
+            self.sentence_open = true;
+            self.push_word(c);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(text: &str) -> (u64, u64) {
+        let mut counter = ProseCounter::new();
+        counter.feed(text.as_bytes());
+        counter.finish()
+    }
+
+    #[test]
+    fn test_sentences_with_abbreviations() {
+        assert_eq!(count("Dr. Smith arrived. He said hi!"), (2, 1));
+        assert_eq!(count("Use tools, e.g. grep. Or not?! Fine..."), (3, 1));
+        assert_eq!(
+            count("Pi is 3.14 roughly. J. R. R. Tolkien wrote books."),
+            (2, 1)
+        );
+        assert_eq!(count("\"Stop.\" She left. (Really.)"), (3, 1));
+        assert_eq!(count("Heading\n\nBody text without a period"), (2, 2));
+    }
+
+    #[test]
+    fn test_unicode_terminators() {
+        assert_eq!(count("今日は晴れ。明日は雨？ はい！"), (3, 1));
+        assert_eq!(count("यह एक वाक्य है। दूसरा।"), (2, 1));
+    }
+
+    #[test]
+    fn test_paragraphs() {
+        assert_eq!(count(""), (0, 0));
+        assert_eq!(count("\n \n\t\n"), (0, 0));
+        assert_eq!(count("a.\nb.\n\n  \nc.\n\n\nd.\n"), (4, 3));
+    }
+
+    #[test]
+    fn test_chunk_boundaries() {
+        let text =
+            "Intro para. Dr. Who? “Quoted.” 終わり。\n\n  Next para e.g. here…\nStill same.\n";
+        let expected = count(text);
+        for split in 0..=text.len() {
+            let mut counter = ProseCounter::new();
+            counter.feed(&text.as_bytes()[..split]);
+            counter.feed(&text.as_bytes()[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+        assert_eq!(expected, (6, 2));
+    }
+}
@@ -0,0 +1,613 @@
+// 📖 I/O strategies (`--io=auto|mmap|read|direct|uring`)
+//
+// Every backend hands a file over in order through `ChunkReader`: plain and
+// tee'd reads, O_DIRECT reads, `MappedWindows` (mmap in sliding 256MB windows,
+// guarded against SIGBUS) and io_uring in uring.rs. `auto` maps mid-sized
+// files, reads small ones (a single read beats setting up a mapping) and
+// streams files too large to map without thrashing the page cache.
+
+use std::fs::File;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoStrategy {
+    Auto,
+    Mmap,
+    Read,
+    Direct,
+    Uring,
+}
+
+// Files below this are cheaper to read than to map
+const MMAP_MIN: u64 = 64 * 1024;
+// Bytes mapped across all files counted at once (MAP_POPULATE faults them all in)
+const MMAP_BUDGET: u64 = 1 << 30;
+
+impl IoStrategy {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(IoStrategy::Auto),
+            "mmap" => Ok(IoStrategy::Mmap),
+            "read" => Ok(IoStrategy::Read),
+            "direct" => Ok(IoStrategy::Direct),
+            "uring" | "io_uring" => Ok(IoStrategy::Uring),
+            _ => Err(format!(
+                "Unknown I/O strategy: {name} (expected auto, mmap, read, direct, or uring)"
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IoStrategy::Auto => "auto",
+            IoStrategy::Mmap => "mmap",
+            IoStrategy::Read => "read",
+            IoStrategy::Direct => "direct",
+            IoStrategy::Uring => "uring",
+        }
+    }
+
+    // Picks a concrete backend for a file of `size` bytes among `files` files
+    pub fn resolve(self, size: u64, files: usize) -> IoStrategy {
+        match self {
+            IoStrategy::Auto if size < MMAP_MIN => IoStrategy::Read,
+            IoStrategy::Auto if size <= MMAP_BUDGET / files.max(1) as u64 => IoStrategy::Mmap,
+            IoStrategy::Auto if cfg!(target_os = "linux") => IoStrategy::Uring,
+            IoStrategy::Auto => IoStrategy::Read,
+            strategy => strategy,
+        }
+    }
+}
+
+// A file's contents, handed out in order one chunk at a time
+pub trait ChunkReader {
+    // The next chunk, or None at end of file
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>>;
+}
+
+// Use 2MB buffers for maximum I/O efficiency
+const BUFFER_SIZE: usize = 2 * 1024 * 1024;
+// Enough bytes for any BOM, so encoding sniffing never sees a partial one
+const MIN_CHUNK: usize = 4;
+
+// 🚀 Plain `read(2)` into one large buffer. Short pipe reads are topped up
+// to `MIN_CHUNK` bytes, so the first chunk always holds any BOM.
+pub struct BufferedReader<R = File> {
+    input: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> BufferedReader<R> {
+    pub fn new(input: R) -> Self {
+        BufferedReader {
+            input,
+            buffer: vec![0u8; BUFFER_SIZE],
+        }
+    }
+}
+
+impl<R: Read> ChunkReader for BufferedReader<R> {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        let mut filled = 0;
+        while filled < MIN_CHUNK {
+            match self.input.read(&mut self.buffer[filled..]) {
+                Ok(0) => break,
+                Ok(bytes_read) => filled += bytes_read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((filled > 0).then(|| &self.buffer[..filled]))
+    }
+}
+
+// 🚰 Forwards everything it reads to `output` before handing it out
+//
+// `write_all` retries partial and interrupted writes. Rust ignores SIGPIPE,
+// so a reader that goes away shows up as a BrokenPipe error: the stream then
+// ends early, and only what was forwarded has been handed out. The first chunk
+// is topped up to `MIN_CHUNK` bytes like `BufferedReader`'s, though each read
+// is forwarded as soon as it arrives.
+pub struct TeeReader<R, W> {
+    input: R,
+    output: W,
+    buffer: Vec<u8>,
+    output_closed: bool,
+    started: bool,
+}
+
+impl<R: Read, W: io::Write> TeeReader<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        TeeReader {
+            input,
+            output,
+            buffer: vec![0u8; BUFFER_SIZE],
+            output_closed: false,
+            started: false,
+        }
+    }
+
+    // True if the stream ended because `output` was closed
+    pub fn output_closed(&self) -> bool {
+        self.output_closed
+    }
+}
+
+impl<R: Read, W: io::Write> ChunkReader for TeeReader<R, W> {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.output_closed {
+            return Ok(None);
+        }
+        let wanted = if self.started { 1 } else { MIN_CHUNK };
+        self.started = true;
+
+        let mut filled = 0;
+        loop {
+            let bytes_read = loop {
+                match self.input.read(&mut self.buffer[filled..]) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+
+            let forwarded = match bytes_read {
+                0 => self.output.flush(),
+                // Flushed at once: stdout holds a partial line back otherwise,
+                // and prompts or binary streams must not wait for a newline
+                _ => self
+                    .output
+                    .write_all(&self.buffer[filled..filled + bytes_read])
+                    .and_then(|()| self.output.flush()),
+            };
+            match forwarded {
+                // What was forwarded before the pipe closed is still handed out
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                    self.output_closed = true;
+                    break;
+                }
+                Err(e) => return Err(e),
+                Ok(()) => filled += bytes_read,
+            }
+            if bytes_read == 0 || filled >= wanted {
+                break;
+            }
+        }
+        Ok((filled > 0).then(|| &self.buffer[..filled]))
+    }
+}
+
+// 🧊 Reads that bypass the page cache, for cold scans that should not evict it
+//
+// O_DIRECT needs block-aligned buffers and lengths; filesystems that refuse it
+// (tmpfs, some network mounts) quietly get plain reads instead.
+#[cfg(unix)]
+pub struct DirectReader {
+    file: File,
+    buffer: Vec<u8>,
+    offset: usize,
+    direct: bool,
+}
+
+#[cfg(unix)]
+impl DirectReader {
+    // Covers the logical block size of every common device
+    const ALIGN: usize = 4096;
+
+    pub fn new(file: File) -> Self {
+        let direct = set_direct(&file, true);
+        let buffer = vec![0u8; BUFFER_SIZE + Self::ALIGN];
+        let offset = buffer.as_ptr().align_offset(Self::ALIGN);
+        DirectReader {
+            file,
+            buffer,
+            offset,
+            direct,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl ChunkReader for DirectReader {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        let buffer = &mut self.buffer[self.offset..self.offset + BUFFER_SIZE];
+        let bytes_read = match self.file.read(buffer) {
+            Err(e) if self.direct && e.raw_os_error() == Some(crate::libc::EINVAL) => {
+                self.direct = false;
+                set_direct(&self.file, false);
+                self.file.read(buffer)?
+            }
+            result => result?,
+        };
+        Ok((bytes_read > 0).then(|| &self.buffer[self.offset..self.offset + bytes_read]))
+    }
+}
+
+// True if the descriptor now bypasses the page cache
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_direct(file: &File, on: bool) -> bool {
+    use crate::libc::{fcntl, F_GETFL, F_SETFL, O_DIRECT};
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    unsafe {
+        let flags = fcntl(fd, F_GETFL);
+        let flags = if on {
+            flags | O_DIRECT
+        } else {
+            flags & !O_DIRECT
+        };
+        flags >= 0 && fcntl(fd, F_SETFL, flags) == 0 && on
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn set_direct(file: &File, on: bool) -> bool {
+    use crate::libc::{fcntl, F_NOCACHE};
+    use std::os::unix::io::AsRawFd;
+
+    unsafe { fcntl(file.as_raw_fd(), F_NOCACHE, on as i32) == 0 && on }
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "linux", target_os = "android", target_vendor = "apple"))
+))]
+fn set_direct(_file: &File, _on: bool) -> bool {
+    false
+}
+
+// 🍃 `--no-cache-pollution`: streams with readahead and drops what was counted
+//
+// The page cache keeps every byte we read; on a shared server that evicts
+// other processes' working sets. Each chunk is handed back to the kernel as
+// soon as the next one is asked for.
+#[cfg(unix)]
+pub struct DropBehind {
+    inner: Box<dyn ChunkReader>,
+    fd: i32,
+    // Start and length of the chunk handed out last
+    offset: u64,
+    len: u64,
+}
+
+#[cfg(unix)]
+impl DropBehind {
+    // `fd` must stay open for as long as `inner` reads from it
+    pub fn new(inner: Box<dyn ChunkReader>, fd: i32) -> Self {
+        fadvise(fd, 0, 0, Advice::Sequential);
+        DropBehind {
+            inner,
+            fd,
+            offset: 0,
+            len: 0,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl ChunkReader for DropBehind {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.len > 0 {
+            fadvise(self.fd, self.offset, self.len, Advice::DontNeed);
+        }
+        self.offset += self.len;
+        let chunk = self.inner.next_chunk()?;
+        self.len = chunk.map_or(0, |chunk| chunk.len() as u64);
+        Ok(chunk)
+    }
+}
+
+// Window sizes; multiples of every page size. Drop-behind scans use small
+// windows so little of the file is resident at once.
+#[cfg(unix)]
+pub const MAP_WINDOW: usize = 256 * 1024 * 1024;
+#[cfg(unix)]
+pub const DROP_WINDOW: usize = 16 * 1024 * 1024;
+
+// 🪟 A file mapped one window at a time, so files larger than the address
+// space (or 32-bit `usize`) still map. Only the current window is mapped; with
+// `drop_behind` each one also leaves the page cache once counted while the
+// kernel reads the next one ahead.
+#[cfg(unix)]
+pub struct MappedWindows<'a> {
+    file: &'a File,
+    size: u64,
+    window: usize,
+    drop_behind: bool,
+    // The current window: file offset, address and length
+    offset: u64,
+    ptr: *mut std::ffi::c_void,
+    len: usize,
+    guard: Option<crate::sigbus::Guard>,
+    unmappable: bool,
+}
+
+#[cfg(unix)]
+impl<'a> MappedWindows<'a> {
+    // `size` is the file size when it was opened; later growth is not counted
+    pub fn new(file: &'a File, size: u64, window: usize, drop_behind: bool) -> Self {
+        MappedWindows {
+            file,
+            size,
+            window,
+            drop_behind,
+            offset: 0,
+            ptr: std::ptr::null_mut(),
+            len: 0,
+            guard: None,
+            unmappable: false,
+        }
+    }
+
+    // The first window could not be mapped: the caller should read the file instead
+    pub fn unmappable(&self) -> bool {
+        self.unmappable
+    }
+
+    // True if the file shrank under a window, whose tail then read back as zeros
+    pub fn truncated(&self) -> bool {
+        self.guard.as_ref().is_some_and(|guard| guard.truncated())
+    }
+
+    fn fd(&self) -> i32 {
+        std::os::unix::io::AsRawFd::as_raw_fd(self.file)
+    }
+
+    fn unmap(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        unsafe { crate::libc::munmap(self.ptr, self.len) };
+        // Unmapped pages can leave the page cache, so unmap them first
+        if self.drop_behind {
+            fadvise(self.fd(), self.offset, self.len as u64, Advice::DontNeed);
+        }
+        self.offset += self.len as u64;
+        self.len = 0;
+    }
+
+    fn fail(&mut self, error: io::Error) -> io::Result<Option<&[u8]>> {
+        self.unmappable = self.offset == 0 && self.len == 0;
+        Err(error)
+    }
+}
+
+#[cfg(unix)]
+impl ChunkReader for MappedWindows<'_> {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        use crate::libc;
+
+        // The last window stays mapped, and guarded, until drop
+        let offset = self.offset + self.len as u64;
+        if offset >= self.size {
+            return Ok(None);
+        }
+
+        let len = (self.size - offset).min(self.window as u64) as usize;
+        let populate = if self.drop_behind {
+            0
+        } else {
+            libc::MAP_POPULATE
+        };
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | populate,
+                self.fd(),
+                offset as i64,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return self.fail(io::Error::last_os_error());
+        }
+
+        // 🛡️ A file truncated under the window reads back as zeros, not SIGBUS.
+        // The guard moves before the old window is unmapped, so it never
+        // covers an address range that is no longer ours.
+        match &mut self.guard {
+            Some(guard) => guard.move_to(ptr as *const u8, len),
+            None => match crate::sigbus::Guard::new(ptr as *const u8, len) {
+                Some(guard) => self.guard = Some(guard),
+                None => {
+                    unsafe { libc::munmap(ptr, len) };
+                    return self.fail(io::Error::other("too many files mapped at once"));
+                }
+            },
+        }
+        self.unmap();
+        self.ptr = ptr;
+        self.len = len;
+
+        unsafe {
+            // Prefetch for sequential access
+            libc::madvise(ptr, len, libc::MADV_SEQUENTIAL);
+            if !self.drop_behind {
+                libc::madvise(ptr, len, libc::MADV_WILLNEED);
+            }
+        }
+        if self.drop_behind {
+            let ahead = offset + len as u64;
+            fadvise(self.fd(), ahead, self.window as u64, Advice::WillNeed);
+        }
+        Ok(Some(unsafe {
+            std::slice::from_raw_parts(ptr as *const u8, len)
+        }))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for MappedWindows<'_> {
+    fn drop(&mut self) {
+        // Unregister from the SIGBUS handler before the window goes away
+        self.guard = None;
+        self.unmap();
+    }
+}
+
+#[cfg(unix)]
+enum Advice {
+    Sequential,
+    WillNeed,
+    DontNeed,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn fadvise(fd: i32, offset: u64, len: u64, advice: Advice) {
+    use crate::libc::{
+        posix_fadvise, POSIX_FADV_DONTNEED, POSIX_FADV_SEQUENTIAL, POSIX_FADV_WILLNEED,
+    };
+
+    let advice = match advice {
+        Advice::Sequential => POSIX_FADV_SEQUENTIAL,
+        Advice::WillNeed => POSIX_FADV_WILLNEED,
+        Advice::DontNeed => POSIX_FADV_DONTNEED,
+    };
+    unsafe { posix_fadvise(fd, offset as i64, len as i64, advice) };
+}
+
+// Other systems have no per-range cache control; readahead still applies
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+fn fadvise(_fd: i32, _offset: u64, _len: u64, _advice: Advice) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(reader: &mut dyn ChunkReader) -> Vec<u8> {
+        let mut contents = Vec::new();
+        while let Some(chunk) = reader.next_chunk().unwrap() {
+            contents.extend_from_slice(chunk);
+        }
+        contents
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(IoStrategy::parse("auto"), Ok(IoStrategy::Auto));
+        assert_eq!(IoStrategy::parse("MMAP"), Ok(IoStrategy::Mmap));
+        assert_eq!(IoStrategy::parse("io_uring"), Ok(IoStrategy::Uring));
+        assert!(IoStrategy::parse("aio").is_err());
+    }
+
+    #[test]
+    fn test_tee_forwards_what_it_hands_out() {
+        let input: Vec<u8> = (0..BUFFER_SIZE + 100).map(|i| (i % 256) as u8).collect();
+        let mut output = Vec::new();
+        let mut tee = TeeReader::new(&input[..], &mut output);
+        let handed_out = drain(&mut tee);
+        assert!(!tee.output_closed());
+        assert!(handed_out == input);
+        assert!(output == input);
+
+        // A closed pipe ends the stream instead of failing it
+        struct Closed;
+        impl io::Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut tee = TeeReader::new(&input[..], Closed);
+        assert!(drain(&mut tee).is_empty());
+        assert!(tee.output_closed());
+    }
+
+    // A pipe handing out its input one byte at a time
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.0.len().min(buf.len()).min(1);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn test_buffered_tops_up_short_reads() {
+        let input = b"\xff\xfeh\0i\0";
+        let mut buffered = BufferedReader::new(Trickle(input));
+        assert_eq!(buffered.next_chunk().unwrap(), Some(&input[..4]));
+        assert_eq!(drain(&mut buffered), b"i\0");
+    }
+
+    #[test]
+    fn test_tee_tops_up_the_first_chunk() {
+        // A UTF-16 BOM split over single-byte reads is still whole in the
+        // first chunk; later chunks are handed out as they arrive
+        let input = b"\xff\xfeh\0i\0";
+        let mut output = Vec::new();
+        let mut tee = TeeReader::new(Trickle(input), &mut output);
+        assert_eq!(tee.next_chunk().unwrap(), Some(&input[..4]));
+        assert_eq!(tee.next_chunk().unwrap(), Some(&input[4..5]));
+        assert_eq!(drain(&mut tee), b"\0");
+        assert_eq!(output, input);
+
+        // Input shorter than a BOM is handed out whole at EOF
+        let mut tee = TeeReader::new(Trickle(b"\xff"), io::sink());
+        assert_eq!(drain(&mut tee), b"\xff");
+    }
+
+    #[test]
+    fn test_auto_heuristic() {
+        assert_eq!(IoStrategy::Auto.resolve(100, 1), IoStrategy::Read);
+        assert_eq!(IoStrategy::Auto.resolve(100 << 20, 1), IoStrategy::Mmap);
+        // Sixteen 100MB files would map more than the budget at once
+        assert_ne!(IoStrategy::Auto.resolve(100 << 20, 16), IoStrategy::Mmap);
+        assert_ne!(IoStrategy::Auto.resolve(4 << 30, 1), IoStrategy::Mmap);
+        assert_eq!(IoStrategy::Direct.resolve(100, 1), IoStrategy::Direct);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_readers_see_the_whole_file() {
+        let path = std::env::temp_dir().join(format!("wc-reader-{}.txt", std::process::id()));
+        // Not a multiple of the block size, so the last direct read is short
+        let contents: Vec<u8> = (0..BUFFER_SIZE * 2 + 12345)
+            .map(|i| b"lorem ipsum\n"[i % 12])
+            .collect();
+        std::fs::write(&path, &contents).unwrap();
+
+        let mut buffered = BufferedReader::new(File::open(&path).unwrap());
+        assert_eq!(drain(&mut buffered), contents);
+        let mut direct = DirectReader::new(File::open(&path).unwrap());
+        assert_eq!(drain(&mut direct), contents);
+        let file = File::open(&path).unwrap();
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(&file);
+        let mut uncached = DropBehind::new(Box::new(BufferedReader::new(file)), fd);
+        assert_eq!(drain(&mut uncached), contents);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_mapped_windows() {
+        let path = std::env::temp_dir().join(format!("wc-windows-{}.txt", std::process::id()));
+        let contents: Vec<u8> = (0..1_000_000).map(|i| b"dolor sit\n"[i % 10]).collect();
+        std::fs::write(&path, &contents).unwrap();
+        let file = File::open(&path).unwrap();
+
+        // Sixteen 64KB windows, the last one short
+        for drop_behind in [false, true] {
+            let size = contents.len() as u64;
+            let mut windows = MappedWindows::new(&file, size, 64 * 1024, drop_behind);
+            assert_eq!(windows.next_chunk().unwrap().unwrap().len(), 64 * 1024);
+            let mut read = contents[..64 * 1024].to_vec();
+            read.extend(drain(&mut windows));
+            assert!(read == contents);
+            assert!(!windows.truncated());
+        }
+
+        // Not mappable at all: the caller falls back to reading
+        let null = File::open("/dev/null").unwrap();
+        let mut windows = MappedWindows::new(&null, 4096, MAP_WINDOW, false);
+        assert!(windows.next_chunk().is_err());
+        assert!(windows.unmappable());
+        std::fs::remove_file(path).unwrap();
+    }
+}
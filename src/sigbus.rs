@@ -0,0 +1,124 @@
+// 🛡️ SIGBUS recovery for files truncated while they are memory-mapped
+//
+// Touching a mapped page past the new end of a shrunken file raises SIGBUS.
+// Every live mapping is registered here; the handler maps zero-filled pages
+// over the vanished tail of the faulting mapping, flags it, and returns so the
+// access is retried. The caller then reports the truncation instead of dying.
+
+use crate::libc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Once;
+
+const MAX_MAPPINGS: usize = 256;
+// A slot that is being filled in
+const CLAIMED: usize = usize::MAX;
+
+struct Slot {
+    start: AtomicUsize,
+    len: AtomicUsize,
+    truncated: AtomicBool,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: Slot = Slot {
+    start: AtomicUsize::new(0),
+    len: AtomicUsize::new(0),
+    truncated: AtomicBool::new(false),
+};
+
+static MAPPINGS: [Slot; MAX_MAPPINGS] = [EMPTY; MAX_MAPPINGS];
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(4096);
+static INSTALL: Once = Once::new();
+
+extern "C" fn on_sigbus(_signal: i32, info: *mut libc::siginfo_t, _context: *mut std::ffi::c_void) {
+    let address = unsafe { (*info).si_addr() } as usize;
+    let page = address & !(PAGE_SIZE.load(Ordering::Relaxed) - 1);
+
+    for slot in &MAPPINGS {
+        let start = slot.start.load(Ordering::Acquire);
+        let len = slot.len.load(Ordering::Acquire);
+        if start == 0 || start == CLAIMED || !(start..start + len).contains(&address) {
+            continue;
+        }
+        // Moved to another window while we looked
+        if slot.start.load(Ordering::Acquire) != start {
+            continue;
+        }
+        // mmap is not formally async-signal-safe, but it is a plain syscall
+        let replaced = unsafe {
+            libc::mmap(
+                page as *mut std::ffi::c_void,
+                start + len - page,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_FIXED | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if replaced != libc::MAP_FAILED {
+            slot.truncated.store(true, Ordering::Release);
+            return;
+        }
+    }
+
+    // Not one of ours: the default action kills the process on the retry
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = libc::SIG_DFL;
+        libc::sigaction(libc::SIGBUS, &action, std::ptr::null_mut());
+    }
+}
+
+fn install() {
+    INSTALL.call_once(|| unsafe {
+        let page_size = libc::sysconf(libc::_SC_PAGESIZE);
+        if page_size > 0 {
+            PAGE_SIZE.store(page_size as usize, Ordering::Relaxed);
+        }
+        let mut action: libc::sigaction = std::mem::zeroed();
+        let handler: extern "C" fn(i32, *mut libc::siginfo_t, *mut std::ffi::c_void) = on_sigbus;
+        action.sa_sigaction = handler as usize;
+        action.sa_flags = libc::SA_SIGINFO;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGBUS, &action, std::ptr::null_mut());
+    });
+}
+
+// Keeps a mapping registered with the handler until dropped (before munmap)
+pub struct Guard {
+    slot: &'static Slot,
+}
+
+impl Guard {
+    // None when every slot is taken; the caller should read the file instead
+    pub fn new(start: *const u8, len: usize) -> Option<Guard> {
+        install();
+        let slot = MAPPINGS.iter().find(|slot| {
+            slot.start
+                .compare_exchange(0, CLAIMED, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        })?;
+        slot.truncated.store(false, Ordering::Relaxed);
+        slot.len.store(len, Ordering::Release);
+        slot.start.store(start as usize, Ordering::Release);
+        Some(Guard { slot })
+    }
+
+    // Follows a file's next window; the truncation flag carries over
+    pub fn move_to(&mut self, start: *const u8, len: usize) {
+        self.slot.start.store(CLAIMED, Ordering::Release);
+        self.slot.len.store(len, Ordering::Release);
+        self.slot.start.store(start as usize, Ordering::Release);
+    }
+
+    // True if part of the mapping vanished and was read back as zeros
+    pub fn truncated(&self) -> bool {
+        self.slot.truncated.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.slot.start.store(0, Ordering::Release);
+    }
+}
@@ -0,0 +1,93 @@
+// ⏱️ `--timings`: wall time, throughput and I/O path per file
+//
+// Each file's time covers opening, sniffing and counting it, measured on the
+// thread that counted it. The total line shows the whole run's wall time.
+
+use crate::reader::IoStrategy;
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Timing {
+    pub elapsed: Duration,
+    // The backend that actually read the file, after any fallback
+    pub io: Option<IoStrategy>,
+}
+
+impl Timing {
+    // Milliseconds, MB/s and I/O path columns for `bytes` read in this time
+    pub fn columns(&self, bytes: u64) -> [String; 3] {
+        let seconds = self.elapsed.as_secs_f64();
+        let throughput = if seconds > 0.0 {
+            bytes as f64 / seconds / 1_000_000.0
+        } else {
+            0.0
+        };
+        [
+            format!("{:8.1}", seconds * 1000.0),
+            format!("{throughput:8.1}"),
+            format!("{:>8}", self.io.map_or("-", IoStrategy::name)),
+        ]
+    }
+}
+
+// Columns for a line with nothing timed (skipped files, language totals)
+pub fn blank_columns() -> [String; 3] {
+    [
+        format!("{:>8}", "-"),
+        format!("{:>8}", "-"),
+        format!("{:>8}", "-"),
+    ]
+}
+
+// How busy the worker threads were, as a summary line. `threads` is how many
+// were spawned: one per file, however many cores there are to run them.
+pub fn utilisation(busy: Duration, wall: Duration, threads: usize, cores: usize) -> String {
+    let capacity = wall.as_secs_f64() * threads.max(1) as f64;
+    let percent = if capacity > 0.0 {
+        (busy.as_secs_f64() / capacity * 100.0).min(100.0)
+    } else {
+        0.0
+    };
+    format!(
+        "{threads} files on {threads} threads, {cores} cores: {:.1} ms wall, {:.1} ms busy, {percent:.0}% thread utilisation",
+        wall.as_secs_f64() * 1000.0,
+        busy.as_secs_f64() * 1000.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns() {
+        let timing = Timing {
+            elapsed: Duration::from_millis(250),
+            io: Some(IoStrategy::Mmap),
+        };
+        let [millis, throughput, io] = timing.columns(500_000_000);
+        assert_eq!(millis, "   250.0");
+        assert_eq!(throughput, "  2000.0");
+        assert_eq!(io, "    mmap");
+
+        let instant = Timing::default().columns(10);
+        assert_eq!(instant[1].trim(), "0.0");
+        assert_eq!(instant[2].trim(), "-");
+    }
+
+    #[test]
+    fn test_utilisation() {
+        let wall = Duration::from_millis(100);
+        // Two threads busy the whole time
+        let line = utilisation(Duration::from_millis(200), wall, 2, 8);
+        assert!(line.starts_with("2 files on 2 threads, 8 cores"), "{line}");
+        assert!(line.ends_with(" 100% thread utilisation"), "{line}");
+        // Sixteen threads, one per file, on four cores: measured against all
+        // sixteen, not against the cores
+        let line = utilisation(Duration::from_millis(800), wall, 16, 4);
+        assert!(
+            line.starts_with("16 files on 16 threads, 4 cores") && line.contains(" 50% "),
+            "{line}"
+        );
+    }
+}
@@ -0,0 +1,580 @@
+// 🪙 Approximate LLM token counting (`--tokens`) with tiktoken BPE vocabularies
+//
+// Text is split into pieces by a hand-written equivalent of each encoding's
+// pre-tokenisation regex, then every piece is byte-pair merged by rank exactly
+// like tiktoken's `encode_ordinary`. Letter and number classes come from std's
+// Unicode properties, so counts can drift slightly from tiktoken on text with
+// combining marks; ASCII and ordinary prose match exactly.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Pattern {
+    // r50k/p50k: 's|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+
+    Gpt2,
+    // Case-insensitive contractions, one-char prefixed words, 1-3 digit groups
+    Cl100k,
+    // Case-aware words with contraction suffixes
+    O200k,
+}
+
+// (`--tokens` name, pre-tokeniser, vocabulary file stem)
+const ENCODINGS: &[(&str, Pattern, &str)] = &[
+    ("cl100k", Pattern::Cl100k, "cl100k_base"),
+    ("o200k", Pattern::O200k, "o200k_base"),
+    ("p50k", Pattern::Gpt2, "p50k_base"),
+    ("r50k", Pattern::Gpt2, "r50k_base"),
+    ("gpt2", Pattern::Gpt2, "r50k_base"),
+];
+
+#[cfg(feature = "bundled-vocab")]
+fn bundled(stem: &str) -> Option<&'static [u8]> {
+    match stem {
+        "cl100k_base" => Some(include_bytes!("../vocab/cl100k_base.tiktoken")),
+        "o200k_base" => Some(include_bytes!("../vocab/o200k_base.tiktoken")),
+        "p50k_base" => Some(include_bytes!("../vocab/p50k_base.tiktoken")),
+        "r50k_base" => Some(include_bytes!("../vocab/r50k_base.tiktoken")),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "bundled-vocab"))]
+fn bundled(_stem: &str) -> Option<&'static [u8]> {
+    None
+}
+
+// FxHash: vocabulary lookups dominate, and the keys are never attacker-chosen
+#[derive(Default)]
+struct FxHasher(u64);
+
+impl FxHasher {
+    #[inline(always)]
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+}
+
+impl Hasher for FxHasher {
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    #[inline(always)]
+    fn write_usize(&mut self, n: usize) {
+        self.add(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+type Ranks = HashMap<Box<[u8]>, u32, BuildHasherDefault<FxHasher>>;
+
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in text {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(output)
+}
+
+// tiktoken format: one `<base64 token> <rank>` pair per line
+fn parse_ranks(data: &[u8], source: &str) -> Result<Ranks, String> {
+    let mut ranks = Ranks::default();
+    for (number, line) in data.split(|&b| b == b'\n').enumerate() {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let entry = line.iter().position(|&b| b == b' ').and_then(|space| {
+            let token = decode_base64(&line[..space])?;
+            let rank = std::str::from_utf8(&line[space + 1..]).ok()?.parse().ok()?;
+            Some((token.into_boxed_slice(), rank))
+        });
+        let Some((token, rank)) = entry else {
+            return Err(format!(
+                "{source}:{}: expected `<base64 token> <rank>`",
+                number + 1
+            ));
+        };
+        ranks.insert(token, rank);
+    }
+    if ranks.is_empty() {
+        return Err(format!("{source}: empty vocabulary"));
+    }
+    Ok(ranks)
+}
+
+#[inline(always)]
+fn is_letter(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+#[inline(always)]
+fn is_number(c: char) -> bool {
+    c.is_numeric()
+}
+
+#[inline(always)]
+fn is_newline(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+// [^\s\p{L}\p{N}]
+#[inline(always)]
+fn is_other(c: char) -> bool {
+    !c.is_whitespace() && !is_letter(c) && !is_number(c)
+}
+
+// o200k's [\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}] and [\p{Ll}\p{Lm}\p{Lo}\p{M}]
+#[inline(always)]
+fn is_upperish(c: char) -> bool {
+    c.is_alphabetic() && !c.is_lowercase()
+}
+
+#[inline(always)]
+fn is_lowerish(c: char) -> bool {
+    c.is_alphabetic() && !c.is_uppercase()
+}
+
+#[inline(always)]
+fn run(chars: &[char], mut i: usize, class: impl Fn(char) -> bool) -> usize {
+    while i < chars.len() && class(chars[i]) {
+        i += 1;
+    }
+    i
+}
+
+// Length of 's 't 're 've 'm 'll 'd at `i`, or 0
+fn contraction(chars: &[char], i: usize, ignore_case: bool) -> usize {
+    if chars.get(i) != Some(&'\'') {
+        return 0;
+    }
+    let at = |k: usize| {
+        chars.get(i + k).map(|&c| {
+            if ignore_case {
+                c.to_ascii_lowercase()
+            } else {
+                c
+            }
+        })
+    };
+    match (at(1), at(2)) {
+        (Some('s' | 't' | 'm' | 'd'), _) => 2,
+        (Some('r' | 'v'), Some('e')) | (Some('l'), Some('l')) => 3,
+        _ => 0,
+    }
+}
+
+// `\s*[\r\n]+` (when `newlines`), then `\s+(?!\S)`, then `\s+`; `more` means
+// non-whitespace follows the end of `chars`
+fn whitespace(chars: &[char], i: usize, more: bool, newlines: bool) -> usize {
+    let end = run(chars, i, char::is_whitespace);
+    if newlines {
+        if let Some(last) = (i..end).rev().find(|&k| is_newline(chars[k])) {
+            return last + 1;
+        }
+    }
+    // The last space is left to prefix the following word
+    if (end < chars.len() || more) && end - i > 1 {
+        end - 1
+    } else {
+        end
+    }
+}
+
+// o200k's two word alternatives: U* L+ 'x? and U+ L* 'x?
+fn o200k_word(chars: &[char], j: usize) -> Option<usize> {
+    let upper_end = run(chars, j, is_upperish);
+    // Lm/Lo are in both classes, so the lowercase run may start inside the U run
+    let lower_start = (j..=upper_end)
+        .rev()
+        .find(|&k| chars.get(k).is_some_and(|&c| is_lowerish(c)));
+    let end = match lower_start {
+        Some(k) => run(chars, k, is_lowerish),
+        None if upper_end > j => upper_end,
+        None => return None,
+    };
+    Some(end + contraction(chars, end, true))
+}
+
+pub struct Tokenizer {
+    name: String,
+    pattern: Pattern,
+    ranks: Ranks,
+}
+
+impl fmt::Debug for Tokenizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tokenizer({}, {} tokens)", self.name, self.ranks.len())
+    }
+}
+
+impl Tokenizer {
+    // `name` picks the pre-tokeniser and bundled ranks; `vocab` replaces the ranks
+    pub fn load(name: &str, vocab: Option<&str>) -> Result<Self, String> {
+        let lower = name.to_ascii_lowercase();
+        let key = lower.strip_suffix("_base").unwrap_or(&lower);
+        let Some(&(_, pattern, stem)) = ENCODINGS.iter().find(|(name, _, _)| *name == key) else {
+            return Err(format!(
+                "Unknown token encoding: {name} (expected cl100k, o200k, p50k, or r50k)"
+            ));
+        };
+
+        let ranks = match vocab {
+            Some(path) => {
+                let data = std::fs::read(path).map_err(|e| format!("--vocab: {path}: {e}"))?;
+                parse_ranks(&data, path)?
+            }
+            None => match bundled(stem) {
+                Some(data) => parse_ranks(data, stem)?,
+                None => {
+                    return Err(format!(
+                        "--tokens={name}: this build has no bundled vocabularies \
+                         (pass --vocab {stem}.tiktoken, or rebuild with `--features bundled-vocab`)"
+                    ))
+                }
+            },
+        };
+
+        Ok(Tokenizer {
+            name: key.to_string(),
+            pattern,
+            ranks,
+        })
+    }
+
+    // Tokens in `text`, which must start at a pre-tokenisation boundary
+    pub fn count(&self, text: &[u8], more: bool) -> usize {
+        let text = String::from_utf8_lossy(text);
+        let chars: Vec<char> = text.chars().collect();
+        let mut buffer = [0u8; 4];
+        let mut piece = Vec::new();
+        let mut tokens = 0;
+
+        let mut i = 0;
+        while i < chars.len() {
+            let end = self.piece_end(&chars, i, more);
+            piece.clear();
+            for &c in &chars[i..end] {
+                piece.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+            tokens += self.piece_tokens(&piece);
+            i = end;
+        }
+        tokens
+    }
+
+    fn piece_end(&self, chars: &[char], i: usize, more: bool) -> usize {
+        let c = chars[i];
+        match self.pattern {
+            Pattern::Gpt2 => {
+                let contracted = contraction(chars, i, false);
+                if contracted > 0 {
+                    return i + contracted;
+                }
+                let j = if c == ' ' { i + 1 } else { i };
+                match chars.get(j) {
+                    Some(&d) if is_letter(d) => run(chars, j, is_letter),
+                    Some(&d) if is_number(d) => run(chars, j, is_number),
+                    Some(&d) if is_other(d) => run(chars, j, is_other),
+                    _ => whitespace(chars, i, more, false),
+                }
+            }
+            Pattern::Cl100k => {
+                let contracted = contraction(chars, i, true);
+                if contracted > 0 {
+                    i + contracted
+                } else if is_letter(c) {
+                    run(chars, i, is_letter)
+                } else if !is_newline(c)
+                    && !is_number(c)
+                    && chars.get(i + 1).is_some_and(|&d| is_letter(d))
+                {
+                    run(chars, i + 1, is_letter)
+                } else if is_number(c) {
+                    run(chars, i, is_number).min(i + 3)
+                } else {
+                    let j = if c == ' ' { i + 1 } else { i };
+                    if chars.get(j).is_some_and(|&d| is_other(d)) {
+                        run(chars, run(chars, j, is_other), is_newline)
+                    } else {
+                        whitespace(chars, i, more, true)
+                    }
+                }
+            }
+            Pattern::O200k => {
+                let prefixed = !is_newline(c) && !is_letter(c) && !is_number(c);
+                if let Some(end) = o200k_word(chars, if prefixed { i + 1 } else { i }) {
+                    end
+                } else if is_number(c) {
+                    run(chars, i, is_number).min(i + 3)
+                } else {
+                    let j = if c == ' ' { i + 1 } else { i };
+                    if chars.get(j).is_some_and(|&d| is_other(d)) {
+                        run(chars, run(chars, j, is_other), |d| {
+                            is_newline(d) || d == '/'
+                        })
+                    } else {
+                        whitespace(chars, i, more, true)
+                    }
+                }
+            }
+        }
+    }
+
+    // Byte-pair merges, lowest rank first, as in tiktoken's `byte_pair_merge`.
+    // Parts are a linked list over byte offsets and candidate pairs sit in a
+    // min-heap, so a long run of one letter is O(n log n) rather than O(n²).
+    fn piece_tokens(&self, piece: &[u8]) -> usize {
+        if piece.len() == 1 || self.ranks.contains_key(piece) {
+            return 1;
+        }
+        let rank = |bytes: &[u8]| self.ranks.get(bytes).copied().unwrap_or(u32::MAX);
+
+        // Part `i` spans piece[i..next[i]] and `ranks[i]` is the rank of it
+        // merged with the part after it; `prev` of the first part is usize::MAX
+        let len = piece.len();
+        let mut next: Vec<usize> = (1..=len).collect();
+        let mut prev: Vec<usize> = (0..len).map(|i| i.wrapping_sub(1)).collect();
+        let pair_rank = |next: &[usize], i: usize| match next[i] {
+            after if after < len => rank(&piece[i..next[after]]),
+            _ => u32::MAX,
+        };
+        let mut ranks: Vec<u32> = (0..len).map(|i| pair_rank(&next, i)).collect();
+
+        // Ties go to the leftmost pair, like tiktoken's linear scan
+        let mut heap: BinaryHeap<Reverse<(u32, usize)>> = ranks
+            .iter()
+            .enumerate()
+            .filter(|&(_, &rank)| rank != u32::MAX)
+            .map(|(i, &rank)| Reverse((rank, i)))
+            .collect();
+        let mut parts = len;
+
+        while let Some(Reverse((pair, i))) = heap.pop() {
+            // Entries left behind by earlier merges, or for merged-away
+            // parts, no longer match the part's rank
+            if ranks[i] != pair {
+                continue;
+            }
+            let merged = next[i];
+            next[i] = next[merged];
+            if next[i] < len {
+                prev[next[i]] = i;
+            }
+            ranks[merged] = u32::MAX;
+            parts -= 1;
+
+            for part in [prev[i], i] {
+                if part < len {
+                    ranks[part] = pair_rank(&next, part);
+                    if ranks[part] != u32::MAX {
+                        heap.push(Reverse((ranks[part], part)));
+                    }
+                }
+            }
+        }
+        parts
+    }
+}
+
+// Last position where pre-tokenisation can restart without context: after a
+// newline before an ASCII letter or digit, or at a space between a visible
+// ASCII character and a letter
+fn safe_cut(text: &[u8], from: usize) -> Option<usize> {
+    (from.max(1)..text.len().saturating_sub(1))
+        .rev()
+        .find(|&p| {
+            (text[p - 1] == b'\n' && text[p].is_ascii_alphanumeric())
+                || (text[p] == b' '
+                    && text[p - 1].is_ascii_graphic()
+                    && text[p + 1].is_ascii_alphabetic())
+        })
+}
+
+// Start of the last UTF-8 character in `text`
+fn char_boundary(text: &[u8]) -> usize {
+    (1..text.len())
+        .rev()
+        .find(|&p| text[p] & 0xC0 != 0x80)
+        .unwrap_or(text.len())
+}
+
+// Streaming counter: text is tokenised up to the last safe cut of each block
+pub struct TokenCounter {
+    tokenizer: Arc<Tokenizer>,
+    pending: Vec<u8>,
+    tokens: u64,
+}
+
+const BLOCK_SIZE: usize = 64 * 1024;
+// Text with no safe cut (one huge word, say) is cut anyway past this size,
+// at a character boundary; counts may then differ by a token or two
+const MAX_PENDING: usize = 16 * BLOCK_SIZE;
+
+impl TokenCounter {
+    pub fn new(tokenizer: Arc<Tokenizer>) -> Self {
+        TokenCounter {
+            tokenizer,
+            pending: Vec::new(),
+            tokens: 0,
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for block in chunk.chunks(BLOCK_SIZE) {
+            let scan_from = self.pending.len().saturating_sub(1);
+            self.pending.extend_from_slice(block);
+            let cut = safe_cut(&self.pending, scan_from).or_else(|| {
+                (self.pending.len() > MAX_PENDING).then(|| char_boundary(&self.pending))
+            });
+            if let Some(cut) = cut {
+                self.tokens += self.tokenizer.count(&self.pending[..cut], true) as u64;
+                self.pending.drain(..cut);
+            }
+        }
+    }
+
+    pub fn finish(self) -> u64 {
+        self.tokens + self.tokenizer.count(&self.pending, false) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A toy vocabulary: every byte, plus a few merges
+    fn toy_tokenizer(pattern: Pattern) -> Tokenizer {
+        let mut ranks = Ranks::default();
+        for byte in 0..=255u8 {
+            ranks.insert(Box::new([byte]) as Box<[u8]>, byte as u32);
+        }
+        for (rank, token) in ["he", "ll", "hell", "hello", " w", "or", " wor", " world"]
+            .iter()
+            .enumerate()
+        {
+            ranks.insert(token.as_bytes().into(), 256 + rank as u32);
+        }
+        Tokenizer {
+            name: "toy".to_string(),
+            pattern,
+            ranks,
+        }
+    }
+
+    fn pieces(pattern: Pattern, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        let tokenizer = toy_tokenizer(pattern);
+        let mut pieces = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let end = tokenizer.piece_end(&chars, i, false);
+            pieces.push(chars[i..end].iter().collect());
+            i = end;
+        }
+        pieces
+    }
+
+    #[test]
+    fn test_base64_and_vocabulary_parsing() {
+        assert_eq!(decode_base64(b"IHdvcmxk").unwrap(), b" world");
+        assert_eq!(decode_base64(b"IQ==").unwrap(), b"!");
+        let ranks = parse_ranks(b"IQ== 0\nIHdvcmxk 1\n", "test").unwrap();
+        assert_eq!(ranks.get(&b" world"[..]), Some(&1));
+        assert!(parse_ranks(b"IQ==\n", "test").is_err());
+    }
+
+    #[test]
+    fn test_pre_tokenisation() {
+        assert_eq!(
+            pieces(Pattern::Cl100k, "I'LL pay 12345 dollars!!\n\n  ok  "),
+            ["I", "'LL", " pay", " ", "123", "45", " dollars", "!!\n\n", " ", " ok", "  "]
+        );
+        assert_eq!(
+            pieces(Pattern::Gpt2, "I'LL pay 12345  x"),
+            ["I", "'", "LL", " pay", " 12345", " ", " x"]
+        );
+        assert_eq!(
+            pieces(Pattern::O200k, "HTTPServer don't\n/path"),
+            ["HTTPServer", " don't", "\n", "/path"]
+        );
+    }
+
+    #[test]
+    fn test_byte_pair_merges() {
+        let tokenizer = toy_tokenizer(Pattern::Cl100k);
+        assert_eq!(tokenizer.piece_tokens(b"hello"), 1);
+        assert_eq!(tokenizer.piece_tokens(b"hellx"), 2);
+        // " hello" is not in the vocabulary: " " + "hello"
+        assert_eq!(tokenizer.count(b"hello world, hello", false), 5);
+    }
+
+    #[test]
+    fn test_streaming_matches_whole_text() {
+        let tokenizer = Arc::new(toy_tokenizer(Pattern::Gpt2));
+        let text = "hello world\n\nsecond  line  here\nthird's 42 items\n  indented\n";
+        let expected = tokenizer.count(text.as_bytes(), false) as u64;
+        for split in 0..=text.len() {
+            let mut counter = TokenCounter::new(Arc::clone(&tokenizer));
+            counter.feed(&text.as_bytes()[..split]);
+            counter.feed(&text.as_bytes()[split..]);
+            assert_eq!(counter.finish(), expected, "split at {split}");
+        }
+    }
+
+    #[test]
+    fn test_long_runs_stay_fast_and_bounded() {
+        let tokenizer = Arc::new(toy_tokenizer(Pattern::Cl100k));
+        // One 80 KB piece, which took minutes here with a quadratic merge
+        let run = "l".repeat(80 * 1024);
+        assert_eq!(tokenizer.piece_tokens(run.as_bytes()), 40 * 1024);
+
+        // A run with no safe cut is still counted in bounded pieces
+        let mut counter = TokenCounter::new(Arc::clone(&tokenizer));
+        for _ in 0..40 {
+            counter.feed("é".repeat(BLOCK_SIZE / 2).as_bytes());
+            assert!(counter.pending.len() <= MAX_PENDING + BLOCK_SIZE);
+        }
+        assert!(counter.finish() >= 40 * BLOCK_SIZE as u64 / 2);
+    }
+
+    #[cfg(feature = "bundled-vocab")]
+    #[test]
+    fn test_bundled_vocabularies() {
+        let cl100k = Tokenizer::load("cl100k", None).unwrap();
+        assert_eq!(cl100k.count(b"hello world", false), 2);
+        assert_eq!(cl100k.count(b"tiktoken is great!", false), 6);
+        let o200k = Tokenizer::load("o200k_base", None).unwrap();
+        assert_eq!(o200k.count(b"hello world", false), 2);
+        assert!(Tokenizer::load("cl99k", None).is_err());
+    }
+}
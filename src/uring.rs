@@ -0,0 +1,538 @@
+// 💍 io_uring reader: several reads in flight, chunks handed out in file order
+//
+// Talks to the kernel through the raw syscalls and shared rings, so it needs
+// no liburing. Each of the `DEPTH` slots owns a buffer and reads one block;
+// when the caller is done with a slot it is refilled with the next block.
+// Kernels without io_uring (or seccomp profiles that block it) give the file
+// back to the caller, which falls back to plain reads.
+
+use crate::libc;
+use crate::reader::ChunkReader;
+use std::fs::File;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const DEPTH: usize = 8;
+const BLOCK_SIZE: usize = 512 * 1024;
+
+const IORING_OP_READV: u8 = 1;
+const IORING_ENTER_GETEVENTS: u32 = 1;
+const IORING_OFF_SQ_RING: i64 = 0;
+const IORING_OFF_CQ_RING: i64 = 0x8000000;
+const IORING_OFF_SQES: i64 = 0x10000000;
+
+#[repr(C)]
+#[derive(Default)]
+struct SqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    flags: u32,
+    dropped: u32,
+    array: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct CqringOffsets {
+    head: u32,
+    tail: u32,
+    ring_mask: u32,
+    ring_entries: u32,
+    overflow: u32,
+    cqes: u32,
+    flags: u32,
+    resv1: u32,
+    user_addr: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Params {
+    sq_entries: u32,
+    cq_entries: u32,
+    flags: u32,
+    sq_thread_cpu: u32,
+    sq_thread_idle: u32,
+    features: u32,
+    wq_fd: u32,
+    resv: [u32; 3],
+    sq_off: SqringOffsets,
+    cq_off: CqringOffsets,
+}
+
+// Submission queue entry, laid out as `struct io_uring_sqe`
+#[repr(C)]
+#[derive(Default)]
+struct Sqe {
+    opcode: u8,
+    flags: u8,
+    ioprio: u16,
+    fd: i32,
+    off: u64,
+    addr: u64,
+    len: u32,
+    rw_flags: u32,
+    user_data: u64,
+    buf_index: u16,
+    personality: u16,
+    splice_fd_in: i32,
+    pad: [u64; 2],
+}
+
+// Completion queue entry, laid out as `struct io_uring_cqe`
+#[repr(C)]
+struct Cqe {
+    user_data: u64,
+    res: i32,
+    flags: u32,
+}
+
+struct Mapping {
+    ptr: *mut std::ffi::c_void,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(fd: i32, len: usize, offset: i64) -> io::Result<Mapping> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd,
+                offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mapping { ptr, len })
+    }
+
+    fn at<T>(&self, offset: u32) -> *mut T {
+        unsafe { self.ptr.cast::<u8>().add(offset as usize).cast() }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr, self.len) };
+    }
+}
+
+struct Ring {
+    fd: i32,
+    sq_tail: *const AtomicU32,
+    sq_mask: u32,
+    sq_array: *mut u32,
+    sqes: *mut Sqe,
+    cq_head: *const AtomicU32,
+    cq_tail: *const AtomicU32,
+    cq_mask: u32,
+    cqes: *const Cqe,
+    // Keep the rings mapped for as long as the pointers above are used
+    _maps: [Mapping; 3],
+}
+
+impl Ring {
+    fn new(entries: u32) -> io::Result<Ring> {
+        let mut params = Params::default();
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_io_uring_setup,
+                entries,
+                &mut params as *mut Params,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = fd as i32;
+
+        let maps = (|| {
+            let sq_len = params.sq_off.array as usize + params.sq_entries as usize * 4;
+            let cq_len = params.cq_off.cqes as usize
+                + params.cq_entries as usize * std::mem::size_of::<Cqe>();
+            let sqes_len = params.sq_entries as usize * std::mem::size_of::<Sqe>();
+            Ok::<_, io::Error>([
+                Mapping::new(fd, sq_len, IORING_OFF_SQ_RING)?,
+                Mapping::new(fd, cq_len, IORING_OFF_CQ_RING)?,
+                Mapping::new(fd, sqes_len, IORING_OFF_SQES)?,
+            ])
+        })();
+        let maps = match maps {
+            Ok(maps) => maps,
+            Err(e) => {
+                unsafe { libc::close(fd) };
+                return Err(e);
+            }
+        };
+
+        let [sq, cq, sqes] = &maps;
+        unsafe {
+            Ok(Ring {
+                fd,
+                sq_tail: sq.at(params.sq_off.tail),
+                sq_mask: *sq.at::<u32>(params.sq_off.ring_mask),
+                sq_array: sq.at(params.sq_off.array),
+                sqes: sqes.at(0),
+                cq_head: cq.at(params.cq_off.head),
+                cq_tail: cq.at(params.cq_off.tail),
+                cq_mask: *cq.at::<u32>(params.cq_off.ring_mask),
+                cqes: cq.at(params.cq_off.cqes),
+                _maps: maps,
+            })
+        }
+    }
+
+    // Queues a vectored read; nothing reaches the kernel until `enter`
+    fn push_readv(&mut self, fd: i32, iovec: *const libc::iovec, offset: u64, user_data: u64) {
+        unsafe {
+            // We are the only producer, so our own tail needs no synchronisation
+            let tail = (*self.sq_tail).load(Ordering::Relaxed);
+            let index = tail & self.sq_mask;
+            *self.sqes.add(index as usize) = Sqe {
+                opcode: IORING_OP_READV,
+                fd,
+                off: offset,
+                addr: iovec as u64,
+                len: 1,
+                user_data,
+                ..Sqe::default()
+            };
+            *self.sq_array.add(index as usize) = index;
+            (*self.sq_tail).store(tail.wrapping_add(1), Ordering::Release);
+        }
+    }
+
+    fn enter(&mut self, to_submit: u32, min_complete: u32) -> io::Result<()> {
+        loop {
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_io_uring_enter,
+                    self.fd,
+                    to_submit,
+                    min_complete,
+                    IORING_ENTER_GETEVENTS,
+                    std::ptr::null::<std::ffi::c_void>(),
+                    0usize,
+                )
+            };
+            if result >= 0 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+
+    // Returns (user_data, result) of the oldest completion, if any
+    fn pop_completion(&mut self) -> Option<(u64, i32)> {
+        unsafe {
+            let head = (*self.cq_head).load(Ordering::Relaxed);
+            if head == (*self.cq_tail).load(Ordering::Acquire) {
+                return None;
+            }
+            let cqe = &*self.cqes.add((head & self.cq_mask) as usize);
+            let completion = (cqe.user_data, cqe.res);
+            (*self.cq_head).store(head.wrapping_add(1), Ordering::Release);
+            Some(completion)
+        }
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    InFlight,
+    Ready,
+}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    offset: u64,
+    len: usize,
+    filled: usize,
+    state: State,
+}
+
+pub struct UringReader {
+    ring: Ring,
+    file: File,
+    // Size when opened; like a mapping, bytes appended later are not read
+    size: u64,
+    buffers: Vec<Vec<u8>>,
+    iovecs: Vec<libc::iovec>,
+    slots: [Slot; DEPTH],
+    next_offset: u64,
+    // The slot holding the next chunk in file order
+    current: usize,
+    // The slot handed out last, refilled on the next call
+    returned: Option<usize>,
+    unsubmitted: u32,
+    in_flight: usize,
+}
+
+// The ring only points into buffers owned by the reader
+unsafe impl Send for UringReader {}
+
+impl UringReader {
+    // Gives the file back when io_uring is unavailable or it is not a regular file
+    pub fn new(file: File) -> Result<UringReader, File> {
+        let size = match file.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => return Err(file),
+        };
+        let Ok(ring) = Ring::new(DEPTH as u32) else {
+            return Err(file);
+        };
+
+        let mut buffers = vec![vec![0u8; BLOCK_SIZE]; DEPTH];
+        let iovecs = buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr().cast(),
+                iov_len: 0,
+            })
+            .collect();
+        let mut reader = UringReader {
+            ring,
+            file,
+            size,
+            buffers,
+            iovecs,
+            slots: [Slot {
+                offset: 0,
+                len: 0,
+                filled: 0,
+                state: State::Idle,
+            }; DEPTH],
+            next_offset: 0,
+            current: 0,
+            returned: None,
+            unsubmitted: 0,
+            in_flight: 0,
+        };
+        for slot in 0..DEPTH {
+            reader.refill(slot);
+        }
+        Ok(reader)
+    }
+
+    // Points an idle slot at the next block of the file
+    fn refill(&mut self, slot: usize) {
+        if self.next_offset >= self.size {
+            self.slots[slot].state = State::Idle;
+            return;
+        }
+        let len = (self.size - self.next_offset).min(BLOCK_SIZE as u64) as usize;
+        self.slots[slot] = Slot {
+            offset: self.next_offset,
+            len,
+            filled: 0,
+            state: State::InFlight,
+        };
+        self.next_offset += len as u64;
+        self.queue(slot);
+    }
+
+    // Queues a read for whatever part of the slot's block is still missing
+    fn queue(&mut self, slot: usize) {
+        let Slot {
+            offset,
+            len,
+            filled,
+            ..
+        } = self.slots[slot];
+        self.iovecs[slot] = libc::iovec {
+            iov_base: self.buffers[slot][filled..].as_mut_ptr().cast(),
+            iov_len: len - filled,
+        };
+        self.ring.push_readv(
+            self.file.as_raw_fd(),
+            &self.iovecs[slot],
+            offset + filled as u64,
+            slot as u64,
+        );
+        self.unsubmitted += 1;
+        self.in_flight += 1;
+    }
+
+    // Submits queued reads and waits for at least `min_complete` of them
+    fn enter(&mut self, min_complete: u32) -> io::Result<()> {
+        let to_submit = std::mem::take(&mut self.unsubmitted);
+        self.ring.enter(to_submit, min_complete)
+    }
+
+    fn complete(&mut self, slot: usize, result: i32) -> io::Result<()> {
+        self.in_flight -= 1;
+        if result < 0 {
+            return Err(io::Error::from_raw_os_error(-result));
+        }
+        let entry = &mut self.slots[slot];
+        entry.filled += result as usize;
+        if result == 0 {
+            // EOF before the size the file had when opened: it shrank, which
+            // is an error like a mapping truncated underneath us
+            let now = self
+                .file
+                .metadata()
+                .map_or(entry.offset + entry.filled as u64, |metadata| {
+                    metadata.len()
+                });
+            return Err(io::Error::other(format!(
+                "file shrank while being read ({} -> {now} bytes)",
+                self.size
+            )));
+        }
+        if entry.filled < entry.len {
+            self.queue(slot);
+        } else {
+            self.slots[slot].state = State::Ready;
+        }
+        Ok(())
+    }
+}
+
+impl ChunkReader for UringReader {
+    fn next_chunk(&mut self) -> io::Result<Option<&[u8]>> {
+        if let Some(slot) = self.returned.take() {
+            self.refill(slot);
+        }
+
+        let slot = self.current;
+        while self.slots[slot].state == State::InFlight {
+            self.enter(1)?;
+            while let Some((user_data, result)) = self.ring.pop_completion() {
+                self.complete(user_data as usize, result)?;
+            }
+        }
+        if self.unsubmitted > 0 {
+            self.enter(0)?;
+        }
+
+        let Slot { filled, state, .. } = self.slots[slot];
+        if state == State::Idle || filled == 0 {
+            return Ok(None);
+        }
+        self.returned = Some(slot);
+        self.current = (slot + 1) % DEPTH;
+        Ok(Some(&self.buffers[slot][..filled]))
+    }
+}
+
+impl Drop for UringReader {
+    fn drop(&mut self) {
+        // The kernel may still be writing into our buffers
+        while self.in_flight > 0 {
+            if self.enter(1).is_err() {
+                std::mem::forget(std::mem::take(&mut self.buffers));
+                return;
+            }
+            while self.ring.pop_completion().is_some() {
+                self.in_flight -= 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("wc-uring-{name}-{}", std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_reads_in_order() {
+        // More blocks than slots, and a short last block
+        let contents: Vec<u8> = (0..BLOCK_SIZE * (DEPTH + 3) + 777)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let path = temp_file("order", &contents);
+
+        // Sandboxes may forbid io_uring; the caller falls back to plain reads
+        if let Ok(mut reader) = UringReader::new(File::open(&path).unwrap()) {
+            let mut read = Vec::new();
+            while let Some(chunk) = reader.next_chunk().unwrap() {
+                read.extend_from_slice(chunk);
+            }
+            assert!(read == contents);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_empty_and_special_files() {
+        let path = temp_file("empty", b"");
+        if let Ok(mut reader) = UringReader::new(File::open(&path).unwrap()) {
+            assert!(reader.next_chunk().unwrap().is_none());
+        }
+        std::fs::remove_file(path).unwrap();
+
+        // Not a regular file: handed back for plain reads
+        assert!(UringReader::new(File::open("/dev/null").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_file_shrinks_while_read() {
+        let contents = vec![b'x'; BLOCK_SIZE * 3];
+        let path = temp_file("shrink", &contents);
+        let file = File::open(&path).unwrap();
+        if let Ok(mut reader) = UringReader::new(file) {
+            // Nothing is submitted until the first chunk is asked for
+            let shrunk = BLOCK_SIZE as u64 + 10;
+            std::fs::OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_len(shrunk)
+                .unwrap();
+            let error = loop {
+                match reader.next_chunk() {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => panic!("a shrunken file read to a clean EOF"),
+                    Err(error) => break error,
+                }
+            };
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "file shrank while being read ({} -> {shrunk} bytes)",
+                    contents.len()
+                )
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dropped_mid_file() {
+        let contents = vec![b'x'; BLOCK_SIZE * DEPTH * 2];
+        let path = temp_file("drop", &contents);
+        if let Ok(mut reader) = UringReader::new(File::open(&path).unwrap()) {
+            assert_eq!(
+                reader.next_chunk().unwrap().map(<[u8]>::len),
+                Some(BLOCK_SIZE)
+            );
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,186 @@
+// ✅ Streaming UTF-8 validation (`--check-utf8` / `--strict-utf8`)
+//
+// Pure-ASCII stretches are skipped eight bytes at a time; everything else goes
+// through a byte-level state machine that follows the Unicode "maximal subpart"
+// rule, so the invalid-sequence count matches the number of U+FFFD that
+// `String::from_utf8_lossy` would produce.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Utf8Report {
+    pub invalid: u64,
+    // Byte offset and 1-based line of the first invalid sequence
+    pub first_invalid: Option<(u64, u64)>,
+}
+
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+const LOW_BITS: u64 = 0x7F7F_7F7F_7F7F_7F7F;
+const NEWLINES: u64 = 0x0A0A_0A0A_0A0A_0A0A;
+
+// Exact number of '\n' bytes in an ASCII word
+#[inline(always)]
+fn count_newlines(word: u64) -> u64 {
+    let x = word ^ NEWLINES;
+    (!(((x & LOW_BITS) + LOW_BITS) | x | LOW_BITS)).count_ones() as u64
+}
+
+pub struct Utf8Validator {
+    report: Utf8Report,
+    offset: u64,
+    // Lines are only tracked until the first error is located
+    line: u64,
+    // Continuation bytes still expected, and the allowed range of the next one
+    need: u8,
+    lower: u8,
+    upper: u8,
+    sequence_start: u64,
+}
+
+impl Default for Utf8Validator {
+    fn default() -> Self {
+        Utf8Validator {
+            report: Utf8Report::default(),
+            offset: 0,
+            line: 1,
+            need: 0,
+            lower: 0x80,
+            upper: 0xBF,
+            sequence_start: 0,
+        }
+    }
+}
+
+impl Utf8Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline(always)]
+    fn invalid(&mut self, at: u64) {
+        self.report.invalid += 1;
+        if self.report.first_invalid.is_none() {
+            self.report.first_invalid = Some((at, self.line));
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) {
+        let mut i = 0;
+        let len = chunk.len();
+
+        while i < len {
+            if self.need == 0 {
+                // 🚀 ASCII fast path
+                while i + 8 <= len {
+                    let word = u64::from_le_bytes(chunk[i..i + 8].try_into().unwrap());
+                    if word & HIGH_BITS != 0 {
+                        break;
+                    }
+                    if self.report.first_invalid.is_none() {
+                        self.line += count_newlines(word);
+                    }
+                    i += 8;
+                }
+                if i >= len {
+                    break;
+                }
+            }
+
+            let byte = chunk[i];
+            let at = self.offset + i as u64;
+            i += 1;
+
+            if self.need > 0 {
+                if (self.lower..=self.upper).contains(&byte) {
+                    self.need -= 1;
+                    self.lower = 0x80;
+                    self.upper = 0xBF;
+                    continue;
+                }
+                // The truncated sequence is one error; `byte` starts afresh
+                self.need = 0;
+                self.lower = 0x80;
+                self.upper = 0xBF;
+                self.invalid(self.sequence_start);
+            }
+
+            self.sequence_start = at;
+            match byte {
+                b'\n' => self.line += 1,
+                0x00..=0x7F => {}
+                0xC2..=0xDF => self.need = 1,
+                0xE0 => (self.need, self.lower) = (2, 0xA0),
+                0xED => (self.need, self.upper) = (2, 0x9F),
+                0xE1..=0xEF => self.need = 2,
+                0xF0 => (self.need, self.lower) = (3, 0x90),
+                0xF4 => (self.need, self.upper) = (3, 0x8F),
+                0xF1..=0xF3 => self.need = 3,
+                _ => self.invalid(at),
+            }
+        }
+
+        self.offset += len as u64;
+    }
+
+    pub fn finish(mut self) -> Utf8Report {
+        if self.need > 0 {
+            self.invalid(self.sequence_start);
+        }
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(data: &[u8], chunk_size: usize) -> Utf8Report {
+        let mut validator = Utf8Validator::new();
+        for chunk in data.chunks(chunk_size.max(1)) {
+            validator.feed(chunk);
+        }
+        validator.finish()
+    }
+
+    fn lossy_errors(data: &[u8]) -> u64 {
+        String::from_utf8_lossy(data)
+            .chars()
+            .filter(|&c| c == char::REPLACEMENT_CHARACTER)
+            .count() as u64
+    }
+
+    #[test]
+    fn test_valid_input() {
+        let text = "plain ascii\nhéllo wörld 🦀 日本語\n".repeat(10);
+        assert_eq!(validate(text.as_bytes(), 7), Utf8Report::default());
+    }
+
+    #[test]
+    fn test_first_invalid_offset_and_line() {
+        let data = b"line one\nline two\nbad \xFF here \xC3\n";
+        let report = validate(data, 5);
+        assert_eq!(report.invalid, 2);
+        assert_eq!(report.first_invalid, Some((22, 3)));
+    }
+
+    #[test]
+    fn test_matches_lossy_decoding() {
+        let samples: &[&[u8]] = &[
+            b"\xC0\x80",
+            b"\xE0\x80\x80",
+            b"\xED\xA0\x80",
+            b"\xF4\x90\x80\x80",
+            b"\xF0\x9F\x98",
+            b"\xE2\x82",
+            b"abc\xF0\x9F\x98\x80\x80def",
+            b"\xC3\x28\xA0\xA1\xE2\x28\xA1",
+        ];
+        for sample in samples {
+            for chunk_size in 1..=sample.len() {
+                assert_eq!(
+                    validate(sample, chunk_size).invalid,
+                    lossy_errors(sample),
+                    "{sample:?} in chunks of {chunk_size}"
+                );
+            }
+        }
+    }
+}
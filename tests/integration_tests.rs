@@ -1,3 +1,6 @@
+// `extract_counts` predates the clippy gate and is kept as written
+#![allow(clippy::expect_fun_call)]
+
 use std::process::Command;
 use std::str;
 
@@ -292,3 +295,539 @@ fn test_performance_large_file() {
     // Allow some variance for test environment differences
     assert!(our_time.as_millis() <= sys_time.as_millis() * 3);
 }
+
+#[test]
+fn test_distinct_counts() {
+    let (exact, _, code) = run_wc(&[
+        "--distinct-lines",
+        "--distinct-words",
+        "--exact",
+        "tests/data/small.txt",
+    ]);
+    assert_eq!(code, 0);
+    let exact: Vec<&str> = exact.split_whitespace().collect();
+
+    let (approx, _, _) = run_wc(&[
+        "--distinct-lines",
+        "--distinct-words",
+        "tests/data/small.txt",
+    ]);
+    let approx: Vec<&str> = approx.split_whitespace().collect();
+
+    // Linear counting is exact in practice for a handful of values
+    assert_eq!(exact, approx);
+    assert_eq!(exact[2], "tests/data/small.txt");
+}
+
+#[test]
+fn test_code_mode_by_language() {
+    let (output, _, code) = run_wc(&["--code", "src/main.rs", "scripts/release.sh"]);
+    assert_eq!(code, 0);
+
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    // Two files, one subtotal per language, then the grand total
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[0][3..], ["Rust", "src/main.rs"]);
+    assert_eq!(rows[1][3..], ["Shell", "scripts/release.sh"]);
+    assert_eq!(rows[2][3..], ["Rust", "total"]);
+    assert_eq!(rows[3][3..], ["Shell", "total"]);
+    assert_eq!(rows[4][3..], ["total"]);
+
+    // code + comments + blanks accounts for every line of the file
+    let (lines, _, _) = run_wc(&["-l", "src/main.rs"]);
+    let lines: usize = lines.split_whitespace().next().unwrap().parse().unwrap();
+    let classified: usize = rows[0][..3]
+        .iter()
+        .map(|n| n.parse::<usize>().unwrap())
+        .sum();
+    assert_eq!(classified, lines);
+}
+
+#[test]
+fn test_sentences_and_paragraphs() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new("./target/release/wc")
+        .arg("-sp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn wc");
+    child
+        .stdin
+        .as_mut()
+        .unwrap()
+        .write_all("Dr. Jones arrived. Was it late?\n\nYes, e.g. 3.5 hours…\n".as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let counts: Vec<&str> = str::from_utf8(&output.stdout)
+        .unwrap()
+        .split_whitespace()
+        .collect();
+
+    assert_eq!(counts, ["3", "2"]);
+}
+
+#[test]
+fn test_markdown_mode() {
+    let (output, _, code) = run_wc(&["--markdown", "CHANGELOG.md"]);
+    assert_eq!(code, 0);
+    let counts: Vec<&str> = output.split_whitespace().collect();
+    let (prose, code_words): (usize, usize) =
+        (counts[0].parse().unwrap(), counts[1].parse().unwrap());
+
+    // Markup and code spans are excluded from the prose words
+    let (words, _, _) = run_wc(&["-w", "CHANGELOG.md"]);
+    let words: usize = words.split_whitespace().next().unwrap().parse().unwrap();
+    assert!(prose > 0 && code_words > 0);
+    assert!(prose + code_words < words);
+}
+
+#[test]
+fn test_match_columns() {
+    let (output, _, code) = run_wc(&[
+        "-l",
+        "--match",
+        "words",
+        "--match",
+        "THE",
+        "-i",
+        "tests/data/small.txt",
+    ]);
+    assert_eq!(code, 0);
+    let columns: Vec<&str> = output.split_whitespace().collect();
+
+    let count = |args: &[&str]| -> String {
+        let output = Command::new("grep")
+            .args(args)
+            .arg("tests/data/small.txt")
+            .output()
+            .expect("Failed to execute grep");
+        let text = str::from_utf8(&output.stdout).unwrap().to_string();
+        if args.contains(&"-c") {
+            text.trim().to_string()
+        } else {
+            text.lines().count().to_string()
+        }
+    };
+
+    assert_eq!(columns[1], count(&["-c", "words"]));
+    assert_eq!(columns[2], count(&["-o", "words"]));
+    assert_eq!(columns[3], count(&["-ci", "the"]));
+    assert_eq!(columns[4], count(&["-oi", "the"]));
+}
+
+#[test]
+fn test_utf16_input_is_decoded() {
+    let text = "Windows report\r\nwith 🦀 surrogate pairs\r\nand äccents\r\n".repeat(50);
+    let mut utf16 = vec![0xFF, 0xFE];
+    utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+
+    let path = std::env::temp_dir().join(format!("wc-utf16-{}.txt", std::process::id()));
+    std::fs::write(&path, &utf16).unwrap();
+    let (output, _, code) = run_wc(&[path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(code, 0);
+
+    let (lines, words, bytes) = extract_counts(&output);
+    assert_eq!((lines, words), (150, 400));
+    // Bytes are still the raw size on disk
+    assert_eq!(bytes, utf16.len());
+}
+
+#[test]
+fn test_check_and_strict_utf8() {
+    let path = std::env::temp_dir().join(format!("wc-invalid-{}.txt", std::process::id()));
+    std::fs::write(&path, b"fine\nstill fine\nbroken \xE2\x82 here \xFF\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let (output, _, code) = run_wc(&["--check-utf8", path]);
+    assert_eq!(code, 0);
+    let columns: Vec<&str> = output.split_whitespace().collect();
+    assert_eq!(columns[..2], ["2", "23:3"]);
+
+    let (_, stderr, code) = run_wc(&["--strict-utf8", path]);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(code, 1);
+    assert!(stderr.contains("invalid UTF-8 at byte 23 (line 3)"));
+
+    let (_, _, code) = run_wc(&["--strict-utf8", "tests/data/small.txt"]);
+    assert_eq!(code, 0);
+}
+
+#[test]
+fn test_eol_report_and_line_ending() {
+    let path = std::env::temp_dir().join(format!("wc-eol-{}.txt", std::process::id()));
+    std::fs::write(&path, b"unix\nwindows\r\nmore\r\nold mac\rno newline").unwrap();
+    let path = path.to_str().unwrap();
+
+    let (output, _, _) = run_wc(&["--eol", path]);
+    let columns: Vec<&str> = output.split_whitespace().collect();
+    assert_eq!(columns[..4], ["1", "2", "1", "1"]);
+
+    let lines = |ending: &str| {
+        let (output, _, _) = run_wc(&["-l", &format!("--line-ending={ending}"), path]);
+        output.split_whitespace().next().unwrap().to_string()
+    };
+    assert_eq!(lines("lf"), "3");
+    assert_eq!(lines("crlf"), "2");
+    assert_eq!(lines("cr"), "3");
+    assert_eq!(lines("any"), "4");
+    std::fs::remove_file(path).unwrap();
+
+    let (_, stderr, code) = run_wc(&["--line-ending=nel", "tests/data/small.txt"]);
+    assert_ne!(code, 0);
+    assert!(stderr.contains("Unknown line ending"));
+}
+
+#[test]
+fn test_graphemes_and_width() {
+    let path = std::env::temp_dir().join(format!("wc-graphemes-{}.txt", std::process::id()));
+    std::fs::write(&path, "héllo 日本\ne\u{301} 👩‍💻🇯🇵\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let (output, _, code) = run_wc(&["--graphemes", "--width", "-c", path]);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(code, 0);
+    let columns: Vec<&str> = output.split_whitespace().collect();
+    assert_eq!(columns[..3], ["38", "14", "16"]);
+}
+
+#[test]
+fn test_token_counts() {
+    let path = std::env::temp_dir().join(format!("wc-tokens-{}.txt", std::process::id()));
+    std::fs::write(&path, "hello world\ntiktoken is great!\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let (output, _, code) = run_wc(&["--tokens=cl100k", path, "tests/data/small.txt"]);
+    assert_eq!(code, 0);
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    // Default columns stay, tokens follow; "hello world\n" + "tiktoken is great!\n"
+    assert_eq!(rows[0][..4], ["2", "5", "31", "9"]);
+    let total: usize = rows[0][3].parse::<usize>().unwrap() + rows[1][3].parse::<usize>().unwrap();
+    assert_eq!(rows[2][3], total.to_string());
+
+    // A local vocabulary file gives the same counts as the bundled copy
+    let (bundled, _, _) = run_wc(&["--tokens=r50k", path]);
+    let (local, _, _) = run_wc(&["--tokens=r50k", "--vocab", "vocab/r50k_base.tiktoken", path]);
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(bundled, local);
+
+    let (_, stderr, code) = run_wc(&["--vocab", "vocab/r50k_base.tiktoken", path]);
+    assert_ne!(code, 0);
+    assert!(stderr.contains("--vocab needs --tokens"));
+}
+
+#[test]
+fn test_binary_policies() {
+    let dir = std::env::temp_dir();
+    let image = dir.join(format!("wc-binary-{}.png", std::process::id()));
+    let object = dir.join(format!("wc-binary-{}.o", std::process::id()));
+    std::fs::write(&image, b"\x89PNG\r\n\x1A\n\0\0\0\rIHDR and more\n").unwrap();
+    std::fs::write(&object, b"text\0with a NUL\n").unwrap();
+    let image = image.to_str().unwrap();
+    let object = object.to_str().unwrap();
+    let text = "tests/data/small.txt";
+
+    let (counted, _, _) = run_wc(&[text]);
+    let (output, stderr, code) = run_wc(&["--binary=skip", text, image, object]);
+    assert_eq!(code, 0);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], counted.trim_end());
+    assert_eq!(
+        lines[1].split_whitespace().take(3).collect::<Vec<_>>(),
+        counted.split_whitespace().take(3).collect::<Vec<_>>()
+    );
+    assert!(stderr.contains(&format!(
+        "skipped 2 binary files: {image} (PNG image), {object} (NUL bytes)"
+    )));
+
+    let (output, stderr, _) = run_wc(&["--binary=warn", object]);
+    std::fs::remove_file(image).unwrap();
+    std::fs::remove_file(object).unwrap();
+    assert_eq!(extract_counts(&output), (1, 4, 16));
+    assert!(stderr.contains("binary file (NUL bytes)"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_binary_policies_on_a_fifo() {
+    // A FIFO cannot seek back after sniffing, so the sniffed prefix must be
+    // counted ahead of the rest of the stream
+    let fifo = std::env::temp_dir().join(format!("wc-binary-fifo-{}", std::process::id()));
+    let status = Command::new("mkfifo").arg(&fifo).status().unwrap();
+    assert!(status.success());
+    let text = "hello fifo world\n".repeat(2_000);
+
+    for policy in ["--binary=skip", "--binary=warn"] {
+        let writer = {
+            let (fifo, text) = (fifo.clone(), text.clone());
+            std::thread::spawn(move || std::fs::write(fifo, text))
+        };
+        let (output, stderr, code) = run_wc(&[policy, fifo.to_str().unwrap()]);
+        writer.join().unwrap().unwrap();
+        assert_eq!(code, 0, "{policy}: {stderr}");
+        assert_eq!(extract_counts(&output), (2_000, 6_000, text.len()), "{policy}");
+    }
+    std::fs::remove_file(&fifo).unwrap();
+}
+
+#[test]
+fn test_io_strategies() {
+    let path = std::env::temp_dir().join(format!("wc-io-{}.txt", std::process::id()));
+    // Several io_uring blocks and direct-I/O buffers, ending mid-block
+    let text = "the quick brown fox\njumps over\tthe lazy dog \n".repeat(100_003);
+    std::fs::write(&path, &text).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (expected, _, _) = run_wc(&["--io=read", path]);
+    assert_eq!(extract_counts(&expected), (200_006, 900_027, text.len()));
+    for io in ["auto", "mmap", "direct", "uring"] {
+        let (output, _, code) = run_wc(&[&format!("--io={io}"), "--eol", "-w", path]);
+        let (reference, _, _) = run_wc(&["--io=read", "--eol", "-w", path]);
+        assert_eq!(code, 0);
+        assert_eq!(output, reference, "--io={io}");
+        let (output, _, _) = run_wc(&[&format!("--io={io}"), path]);
+        assert_eq!(output, expected, "--io={io}");
+    }
+    std::fs::remove_file(path).unwrap();
+
+    let (_, stderr, code) = run_wc(&["--io=aio", "tests/data/small.txt"]);
+    assert_eq!(code, 1);
+    assert!(stderr.contains("Unknown I/O strategy"));
+}
+
+#[test]
+fn test_no_cache_pollution() {
+    let path = std::env::temp_dir().join(format!("wc-uncached-{}.txt", std::process::id()));
+    // Spans two 16MB mapped windows, with a multi-byte character across the seam
+    let mut text = "a".repeat(16 * 1024 * 1024 - 1);
+    text.push_str("é lorem\nipsum dolor\n");
+    std::fs::write(&path, &text).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (expected, _, _) = run_wc(&["--io=read", "-lwc", "--graphemes", path]);
+    for io in ["auto", "mmap", "read", "direct", "uring"] {
+        let io = format!("--io={io}");
+        let (output, _, code) = run_wc(&[&io, "--no-cache-pollution", "-lwc", "--graphemes", path]);
+        assert_eq!(code, 0);
+        assert_eq!(output, expected, "{io}");
+    }
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(
+        expected.split_whitespace().take(4).collect::<Vec<_>>(),
+        [
+            "2",
+            "4",
+            &text.len().to_string(),
+            &(text.chars().count()).to_string()
+        ]
+    );
+}
+
+#[test]
+fn test_tee_mode() {
+    use std::io::{Read, Write};
+    use std::process::Stdio;
+
+    let run_tee = |args: &[&str], input: &[u8]| {
+        let mut child = Command::new("./target/release/wc")
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn wc");
+        let mut stdin = child.stdin.take().unwrap();
+        let input = input.to_vec();
+        let writer = std::thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output().unwrap();
+        writer.join().unwrap().unwrap();
+        output
+    };
+
+    // Data passes through byte for byte; the counts go to stderr
+    let input: Vec<u8> = "tee me\nthrough\0 the pipe\n".repeat(200_000).into_bytes();
+    let output = run_tee(&["--tee"], &input);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout == input);
+    let report = str::from_utf8(&output.stderr).unwrap();
+    assert_eq!(extract_counts(report), (400_000, 1_000_000, input.len()));
+
+    let report_file = std::env::temp_dir().join(format!("wc-tee-{}.txt", std::process::id()));
+    let report_path = report_file.to_str().unwrap();
+    let output = run_tee(&["--tee", "-l", "--report-to", report_path], b"a\nb\n");
+    assert_eq!(output.stdout, b"a\nb\n");
+    assert!(output.stderr.is_empty());
+    assert_eq!(std::fs::read_to_string(&report_file).unwrap(), "       2\n");
+    std::fs::remove_file(&report_file).unwrap();
+
+    // The consumer goes away early: wc stops, reports what it forwarded and
+    // exits like a SIGPIPE'd pipeline stage, without waiting for EOF
+    let mut child = Command::new("./target/release/wc")
+        .args(["--tee", "-c"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn wc");
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || {
+        let block = vec![b'y'; 64 * 1024];
+        while stdin.write_all(&block).is_ok() {}
+    });
+    let mut stdout = child.stdout.take().unwrap();
+    let mut head = [0u8; 1000];
+    stdout.read_exact(&mut head).unwrap();
+    drop(stdout);
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap();
+    assert_eq!(output.status.code(), Some(141));
+    let forwarded: usize = str::from_utf8(&output.stderr)
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap();
+    assert!(forwarded >= 1000);
+
+    // A partial line is forwarded while the producer is still writing
+    let mut child = Command::new("./target/release/wc")
+        .arg("--tee")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn wc");
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"partial").unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let reader = std::thread::spawn(move || {
+        let mut partial = [0u8; 7];
+        let read = stdout.read_exact(&mut partial).map(|()| partial);
+        tx.send(read.is_ok()).unwrap();
+        read
+    });
+    let arrived = rx.recv_timeout(std::time::Duration::from_secs(5));
+    stdin.write_all(b" line\n").unwrap();
+    drop(stdin);
+    assert_eq!(arrived, Ok(true), "partial line held back until EOF");
+    assert_eq!(&reader.join().unwrap().unwrap(), b"partial");
+    child.wait().unwrap();
+
+    let (_, stderr, code) = run_wc(&["--tee", "tests/data/small.txt"]);
+    assert_eq!(code, 1);
+    assert!(stderr.contains("--tee forwards standard input"));
+    let (_, stderr, code) = run_wc(&["--report-to", report_path, "tests/data/small.txt"]);
+    assert_eq!(code, 1);
+    assert!(stderr.contains("--report-to needs --tee"));
+}
+
+#[test]
+fn test_progress() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    // Counts are unchanged; a quick run ends before the first progress line
+    let files = ["tests/data/small.txt", "tests/data/trivial.txt"];
+    let (plain, _, _) = run_wc(&files);
+    let (with_progress, stderr, code) = run_wc(&["--progress", files[0], files[1]]);
+    assert_eq!(code, 0);
+    assert_eq!(with_progress, plain);
+    assert!(stderr.is_empty(), "{stderr}");
+    let (single, _, code) = run_wc(&["--progress", "-l", files[0]]);
+    assert_eq!(code, 0);
+    assert_eq!(single, run_wc(&["-l", files[0]]).0);
+
+    // Stdin is streamed under --progress but still honours its BOM
+    let mut child = Command::new("./target/release/wc")
+        .args(["--progress"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn wc");
+    let input: Vec<u8> = b"\xff\xfe"
+        .iter()
+        .copied()
+        .chain("hi there\n".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = str::from_utf8(&output.stdout).unwrap();
+    assert_eq!(extract_counts(stdout), (1, 2, input.len()));
+}
+
+#[test]
+fn test_timings() {
+    // Counts, then milliseconds, MB/s and the I/O path that read the file
+    let columns = |output: &str, line: usize| -> Vec<String> {
+        let line = output.lines().nth(line).unwrap();
+        line.split_whitespace().map(str::to_string).collect()
+    };
+    for io in ["mmap", "read"] {
+        let io_flag = format!("--io={io}");
+        let (output, stderr, code) = run_wc(&["--timings", &io_flag, "tests/data/small.txt"]);
+        assert_eq!(code, 0);
+        assert!(stderr.is_empty());
+        let fields = columns(&output, 0);
+        assert_eq!(fields.len(), 7, "{output}");
+        let plain = run_wc(&["tests/data/small.txt"]).0;
+        assert_eq!(extract_counts(&output), extract_counts(&plain));
+        assert!(fields[3].parse::<f64>().is_ok() && fields[4].parse::<f64>().is_ok());
+        assert_eq!(fields[5], io);
+    }
+
+    // The total line shows the whole run; stderr sums up the threads
+    let (output, stderr, code) = run_wc(&[
+        "--timings",
+        "-l",
+        "tests/data/small.txt",
+        "tests/data/trivial.txt",
+    ]);
+    assert_eq!(code, 0);
+    let total = columns(&output, 2);
+    assert_eq!(total.last().unwrap(), "total");
+    assert_eq!(total[3], "-");
+    assert!(stderr.starts_with("wc: timings: 2 files on "), "{stderr}");
+    assert!(stderr.contains("thread utilisation"), "{stderr}");
+}
+
+#[test]
+fn test_bench_subcommand() {
+    let (output, stderr, code) = run_wc(&["bench", "--size=64K", "--trials=2", "--warmup=0"]);
+    assert_eq!(code, 0, "{stderr}");
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .skip(2)
+        .map(|line| line.split_whitespace().collect())
+        .collect();
+    for corpus in ["ascii", "utf8", "long-lines", "whitespace", "binary"] {
+        let benches: Vec<&str> = rows
+            .iter()
+            .filter(|row| row[0] == corpus)
+            .map(|row| row[1])
+            .collect();
+        assert!(
+            benches.starts_with(&["kernel", "stream", "mmap", "read"]),
+            "{output}"
+        );
+    }
+    assert!(rows.iter().all(|row| row[2].parse::<f64>().unwrap() > 0.0));
+
+    let (json, _, code) = run_wc(&["bench", "--size=4K", "--trials=1", "--json"]);
+    assert_eq!(code, 0);
+    assert!(json.starts_with("{\"size\":4096,\"trials\":1,\"warmup\":2,\"results\":[{\"corpus\":\"ascii\",\"bench\":\"kernel\",\"gbps\":"));
+    assert!(json.trim_end().ends_with("}]}"));
+
+    let (_, stderr, code) = run_wc(&["bench", "--trials=many"]);
+    assert_eq!(code, 1);
+    assert!(stderr.contains("Invalid --trials value: many"));
+}
@@ -0,0 +1,330 @@
+// 🎲 Property-based and differential tests against a reference model
+//
+// Every fast path must agree with `reference`, the slowest obviously correct
+// counter there is, on generated inputs. The generator is seeded: a failure
+// prints its seed, and WC_PROPTEST_SEED=<seed> replays it. WC_PROPTEST_CASES
+// raises the number of random cases per property (default 200).
+
+use std::io::{self, BufRead, Read, Write};
+use std::process::{Command, Stdio};
+use ultra_blazing_wc::{count_bytes_blazing_speed, Counter, CountingReader, Counts};
+
+// Lines are '\n' bytes; words are maximal runs of bytes above b' ', which is
+// where `wc` draws the line between whitespace and word characters
+fn reference(data: &[u8]) -> Counts {
+    Counts {
+        lines: data.iter().filter(|&&byte| byte == b'\n').count() as u64,
+        words: data
+            .split(|&byte| byte <= b' ')
+            .filter(|word| !word.is_empty())
+            .count() as u64,
+        chars: data.len() as u64,
+    }
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n.max(1) as u64) as usize
+    }
+
+    // Mostly the bytes either side of every boundary the kernel cares about
+    fn input(&mut self, max_len: usize) -> Vec<u8> {
+        const INTERESTING: &[u8] = b"ab \n\t\r\x0b\x0c\x00\x1f\x20\x21\x7f\x80\xc3\xa9\xff";
+        let len = match self.below(4) {
+            // Around the 8-byte unrolling
+            0 => self.below(24),
+            _ => self.below(max_len + 1),
+        };
+        (0..len)
+            .map(|_| match self.below(8) {
+                0 => self.next() as u8,
+                _ => INTERESTING[self.below(INTERESTING.len())],
+            })
+            .collect()
+    }
+
+    // Splits `len` bytes into random chunk lengths, empty chunks included
+    fn chunking(&mut self, len: usize) -> Vec<usize> {
+        let mut chunks = Vec::new();
+        let mut left = len;
+        while left > 0 {
+            let chunk = match self.below(3) {
+                0 => self.below(3),
+                1 => self.below(16),
+                _ => self.below(left + 1),
+            }
+            .min(left);
+            chunks.push(chunk);
+            left -= chunk;
+        }
+        chunks
+    }
+}
+
+// Runs `property` on `cases` seeded generators, naming the seed on failure
+fn check(name: &str, mut property: impl FnMut(&mut Rng)) {
+    let cases = env_number("WC_PROPTEST_CASES").unwrap_or(200);
+    let seeds: Vec<u64> = match env_number("WC_PROPTEST_SEED") {
+        Some(seed) => vec![seed],
+        None => (1..=cases)
+            .map(|case| case.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect(),
+    };
+    for seed in seeds {
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            property(&mut Rng(seed));
+        }));
+        if let Err(panic) = outcome {
+            eprintln!("{name} failed; replay with WC_PROPTEST_SEED={seed}");
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+#[test]
+fn kernel_matches_reference_on_every_short_input() {
+    // Every input up to 7 bytes over one byte of each kind
+    let alphabet = [b'a', b' ', b'\n', 0x80];
+    for len in 0..=7u32 {
+        for index in 0..alphabet.len().pow(len) {
+            let data: Vec<u8> = (0..len)
+                .map(|position| alphabet[index / alphabet.len().pow(position) % alphabet.len()])
+                .collect();
+            assert_eq!(
+                count_bytes_blazing_speed(&data),
+                reference(&data),
+                "{data:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn kernel_matches_reference() {
+    check("kernel_matches_reference", |rng| {
+        let data = rng.input(4096);
+        assert_eq!(
+            count_bytes_blazing_speed(&data),
+            reference(&data),
+            "{data:?}"
+        );
+    });
+}
+
+#[test]
+fn counter_agrees_at_every_split() {
+    check("counter_agrees_at_every_split", |rng| {
+        let data = rng.input(96);
+        let expected = reference(&data);
+        for first in 0..=data.len() {
+            for second in first..=data.len() {
+                let mut counter = Counter::new();
+                counter.feed(&data[..first]);
+                counter.feed(&data[first..second]);
+                counter.feed(&data[second..]);
+                assert_eq!(
+                    counter.finish(),
+                    expected,
+                    "{data:?} split at {first}, {second}"
+                );
+            }
+        }
+    });
+}
+
+#[test]
+fn counter_agrees_on_random_chunking() {
+    check("counter_agrees_on_random_chunking", |rng| {
+        let data = rng.input(8192);
+        let mut counter = Counter::new();
+        let mut fed = 0;
+        for chunk in rng.chunking(data.len()) {
+            counter.feed(&data[fed..fed + chunk]);
+            fed += chunk;
+            // Running counts are those of the prefix, except a word cut in two
+            let prefix = reference(&data[..fed]);
+            let snapshot = counter.snapshot();
+            assert_eq!(
+                (snapshot.lines, snapshot.chars),
+                (prefix.lines, prefix.chars)
+            );
+            assert!(snapshot.words == prefix.words || snapshot.words + 1 == prefix.words);
+        }
+        assert_eq!(counter.finish(), reference(&data));
+    });
+}
+
+#[test]
+fn writer_and_readers_agree_on_any_buffer_size() {
+    // Hands out at most the next planned number of bytes per read
+    struct Trickle<'a> {
+        data: &'a [u8],
+        reads: std::vec::IntoIter<usize>,
+    }
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let want = self.reads.next().unwrap_or(self.data.len()).max(1);
+            let len = want.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    check("writer_and_readers_agree_on_any_buffer_size", |rng| {
+        let data = rng.input(8192);
+        let expected = reference(&data);
+
+        let mut writer = Counter::new();
+        let mut written = 0;
+        for chunk in rng.chunking(data.len()) {
+            writer.write_all(&data[written..written + chunk]).unwrap();
+            written += chunk;
+        }
+        assert_eq!(writer.finish(), expected);
+
+        // `Read` with caller buffers of random sizes
+        let source = Trickle {
+            data: &data,
+            reads: rng.chunking(data.len()).into_iter(),
+        };
+        let mut reader = CountingReader::new(source);
+        let mut buf = vec![0u8; 1 + rng.below(600)];
+        let mut read_back = Vec::new();
+        loop {
+            let size = 1 + rng.below(buf.len());
+            match reader.read(&mut buf[..size]).unwrap() {
+                0 => break,
+                n => read_back.extend_from_slice(&buf[..n]),
+            }
+        }
+        assert!(read_back == data);
+        assert_eq!(reader.counts(), expected);
+
+        // `BufRead` consuming random amounts of each buffer
+        let source = Trickle {
+            data: &data,
+            reads: rng.chunking(data.len()).into_iter(),
+        };
+        let mut reader =
+            CountingReader::new(io::BufReader::with_capacity(1 + rng.below(300), source));
+        loop {
+            let available = reader.fill_buf().unwrap().len();
+            if available == 0 {
+                break;
+            }
+            reader.consume(1 + rng.below(available));
+        }
+        assert_eq!(reader.counts(), expected);
+    });
+}
+
+// The binary's `-lwc` output for `input` on stdin, as counts
+fn run_stdin(args: &[&str], input: &[u8]) -> Counts {
+    let mut child = Command::new("./target/release/wc")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to spawn wc");
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    assert!(output.status.success());
+    parse_counts(
+        std::str::from_utf8(&output.stdout)
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap(),
+    )
+}
+
+fn parse_counts(line: &str) -> Counts {
+    let numbers: Vec<u64> = line
+        .split_whitespace()
+        .take(3)
+        .map(|field| field.parse().unwrap())
+        .collect();
+    Counts {
+        lines: numbers[0],
+        words: numbers[1],
+        chars: numbers[2],
+    }
+}
+
+#[test]
+fn binary_paths_agree_with_reference() {
+    // Sizes either side of the read/mmap cutoff and the 2MB read buffer. The
+    // encoding is pinned so a random BOM-like prefix is not decoded.
+    let sizes = [0, 1, 7, 4095, 70_000, 2 * 1024 * 1024 + 3];
+    let mut rng = Rng(env_number("WC_PROPTEST_SEED").unwrap_or(0x5eed));
+    let dir = std::env::temp_dir().join(format!("wc-properties-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut files = Vec::new();
+    let mut expected = Vec::new();
+    for (index, size) in sizes.into_iter().enumerate() {
+        let mut data = Vec::with_capacity(size);
+        while data.len() < size {
+            data.extend(rng.input(4096));
+        }
+        data.truncate(size);
+        let path = dir.join(format!("input-{index}"));
+        std::fs::write(&path, &data).unwrap();
+        files.push(path.to_str().unwrap().to_string());
+        expected.push(reference(&data));
+
+        for args in [
+            &["--encoding=utf-8"][..],
+            &["--encoding=utf-8", "--progress"],
+        ] {
+            assert_eq!(run_stdin(args, &data), reference(&data), "stdin {args:?}");
+        }
+    }
+
+    // Each file alone through every backend, then all at once on worker threads
+    for io in ["auto", "mmap", "read", "direct", "uring"] {
+        let io = format!("--io={io}");
+        for (file, expected) in files.iter().zip(&expected) {
+            let output = Command::new("./target/release/wc")
+                .args(["--encoding=utf-8", &io, file])
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8(output.stdout).unwrap();
+            assert_eq!(parse_counts(&stdout), *expected, "{io} {file}");
+        }
+
+        let output = Command::new("./target/release/wc")
+            .args(["--encoding=utf-8", &io])
+            .args(&files)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), files.len() + 1);
+        let mut total = Counts::default();
+        for (line, expected) in lines.iter().zip(&expected) {
+            assert_eq!(parse_counts(line), *expected, "{io} {line}");
+            total.add(expected);
+        }
+        assert_eq!(parse_counts(lines[files.len()]), total, "{io} total");
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}